@@ -271,12 +271,28 @@ Before 0.1.8 we had an implicit default tag of "kind" for enums. Now we don't (a
 
 Serde attributes understood
 
-* `rename`, `rename_all`:
+* `rename`, `rename_all`: names that aren't valid identifiers (`"content-type"`, `"@id"`, `"2fa"`) are
+  quoted as property keys and the type guards use `obj["content-type"]`. This goes for enum tags and content too.
 * `tag`:
+  with no `content` a newtype variant `V(Inner)` merges its fields next to the tag
+  and becomes `{ tag: "V" } & Inner`.
+  On a struct with named fields `#[serde(tag="type")]` adds the literal property `type: "StructName"`
+  which the type guard also checks.
 * `content`:
+* `other`: the catch-all unit variant widens the tag to `string` e.g. `{ tag: string }`.
+* `untagged`: on a whole enum or on single variants. Untagged variants are members of the union
+  as they stand and the type guard tries them only after the tagged ones.
 * `skip`: (`typescript-definitions` also skips - by default -  PhantomData fields ... sorry ghost who walks)
 * serialize_with="typescript_definitions::as_byte_string"
 * transparent: NewTypes are automatically transparent. Structs with a single field can be marked transparent.
+* `into`: the type is described (and guarded) as its proxy e.g. `#[serde(into="Proxy")]` gives `export type S = Proxy;`.
+  If you are describing what you *send back* to rust add `#[ts(deserialize)]` and
+  `from` or `try_from` is used instead.
+* `skip_serializing_if`, `default`: the field may be missing so it becomes an optional `name?: T` property.
+* `flatten`: a flattened field of type `Base` gives the intersection `Base & { ...other fields }`.
+  A flattened `HashMap<String, V>` (or `BTreeMap`) catching unknown keys becomes an index signature
+  `{ ...other fields } & { [key: string]: V }` and the type guard only checks the leftover keys against `V`
+  (unless there is also a flattened struct: we don't know its keys so the map values aren't checked).
 
 `serialize_with`, if placed on a `[u8]` or `Vec<u8>` field, will take that field to be a string. (And serde_json will output a `\xdd` encoded string of the array. *or* you can create your own... just ensure to name it `as_byte_string`)
 
@@ -297,10 +313,6 @@ println!("{}", S::type_script_ify());
 
  prints `export type S = { image: string, buffer: number[] };`.
 
-Serde attributes understood but *rejected*:
-
-* `flatten` (this will produce a panic). Probably will never be fixed.
-
 All others are just ignored.
 
 If you have specialized serialization then you
@@ -341,6 +353,27 @@ pub struct Chrono {
 }
 ```
 
+Two types with the same name (in different modules) would clash in the generated `.d.ts`, so
+`#[ts(rename="ServerConfig")]` on the type changes its typescript name, its type guard
+(`isServerConfig`) and the `TS_EXPORT_SERVERCONFIG` constant. Refer to it from other types with
+`#[ts(ts_as="ServerConfig")]`.
+
+Fields can also look different to typescript than to serde (the type guard follows suit):
+
+* `#[ts(skip)]`: leave the field out of the typescript type and guard.
+* `#[ts(rename="...")]`: the field's name in typescript.
+* `#[ts(optional)]`: the field may be missing (`key?: T`).
+* `#[ts(readonly)]`: the field is `readonly`.
+* `#[ts(unchecked)]`: don't require the field's types to be `TypeScriptify` too (see [Problems](#Problems)).
+
+Enum variants take their own options:
+
+* `#[ts(skip)]`: leave the variant out of the typescript type and guard (serde still sees it).
+* `#[ts(rename="...")]`: the variant's name (or tag value) in typescript.
+* `#[ts(ts_type="...")]`: the typescript type of the whole variant, including any tag.
+* `#[ts(guard=false)]`: the type guard only checks the variant's tag, not its contents
+  (an untagged variant then matches anything).
+
 ## <a name='TypeGuards'></a>Type Guards
 
 See [type guards](https://www.typescriptlang.org/docs/handbook/advanced-types.html).
@@ -360,6 +393,13 @@ With the feature *on* you can turn guard generation *off* for any struct/enum wi
 If your struct has a long list of data as `Vec<data>` then you can prevent a type check of the entire array with a field attribute `#[ts(array_check="first")]`
 which will check only the first row.
 
+Guards ignore keys that are not part of the type. If the struct/enum has `#[serde(deny_unknown_fields)]`
+(or, for types that don't use that serde attribute, `#[ts(deny_unknown)]`) then the guard will
+reject objects with any undeclared keys (enum `tag` and `content` keys are of course allowed).
+`#[ts(strict)]` does this too *as well as* rejecting rust types we can't map to typescript
+(see [Problems](#Problems)): add `#[ts(strict, deny_unknown=false)]` if you only want the latter.
+Setting strict crate wide with `TFY_STRICT` leaves the guards alone.
+
 ### Example
 
 ```rust
@@ -370,10 +410,11 @@ pub struct Maybe {
     maybe : Option<String>
 }
 
+# #[cfg(feature = "type-guards")]
 println!("{}", Maybe::type_script_guard().unwrap());
 ```
 
-will print (after passing through prettier):
+will print:
 
 ```typescript
 export const isMaybe = (obj: any): obj is Maybe => {
@@ -429,6 +470,15 @@ markup
 * `ts_guard`: verify the type as if it was this
   typescript type.
 
+The guard is generated from a (small) subset of typescript: `number`, `string`,
+`boolean`, `object`, `null`, `{a: T, b: U}`, `{[key: string]: T}`, tuples, arrays
+and unions. A string it can't parse is a compile error pointing at the string,
+e.g. `#[ts(ts_guard = "{[key: string number]: number}")]` gives:
+
+```text
+can't parse "{[key: string number]: number}": expected `]`, found `number` at column 15
+```
+
 
 ### <a name='LimitationsofGenerics'></a>Limitations of Generics
 
@@ -475,10 +525,12 @@ pub struct DependsOnValue {
 }
 ```
 *OR* you will have to rewrite the generated guard
-for generic type `value: T` yourself. viz:
+for generic type `value: T` yourself. The generated guard for the generic parameter is
+named after both the type and the parameter (`isValue__T`) so that guards
+for different types don't collide. viz:
 
 ```typescript
-const isT = <T>(o: any, typename: string): o is T => {
+const isValue__T = <T>(o: any, typename: string): o is T => {
     // typename is the stringified type that we are
     // expecting e.g. `number` or `{a: number, b: string}[]` etc.
     // 
@@ -491,14 +543,16 @@ const isT = <T>(o: any, typename: string): o is T => {
 }
 ```
 
-Watch out for function name collisions especially if you use simple names such as `T`, for a generic
-type name.
+Rust names that are reserved words in typescript (e.g. a generic parameter `number` or a type `r#delete`)
+get a trailing underscore: `number_`, `delete_`.
 
 The generated output file should really be passed through something like [prettier](https://www.npmjs.com/package/prettier).
 
 ## <a name='Examples'></a>Examples
 
-Top level doc (`///` or `//!` ) comments are converted to javascript (line) comments:
+Doc (`///` or `/** */`) comments on the type, its fields and its enum variants are converted to
+JSDoc comments (so your IDE shows them on hover). Markdown, including blank lines and indented
+code, is kept as is:
 
 ```rust
 use serde::Serialize;
@@ -506,16 +560,27 @@ use typescript_definitions::{TypeScriptify, TypeScriptifyTrait};
 #[derive(Serialize, TypeScriptify)]
 /// This is some API Event.
 struct Event {
+    /// what happened
     what : String,
     pos : Vec<(i32,i32)>
 }
 
 assert_eq!(Event::type_script_ify(), "\
-// This is some API Event.
-export type Event = { what: string; pos: [ number , number ][] };"
+/** This is some API Event. */
+export type Event = {
+  /** what happened */
+  what: string;
+  pos: [number, number][];
+};"
 )
 ```
 
+An object type with documented fields is always printed one field per line.
+
+`#[deprecated(since = "...", note = "...")]` adds a JSDoc `@deprecated` tag so typescript users get the
+same warning. `#[ts(example = "...")]` and `#[ts(see = "...")]` (on a type, field or variant) add
+`@example` and `@see` tags.
+
 ## <a name='Problems'></a>Problems
 
 Oh yes there are problems...
@@ -524,7 +589,7 @@ Currently `typescript-descriptions` will not fail (AFAIK) even for structs and e
 and closures `Fn(A,B) -> C` (generates `(A,B) => C`). These make no sense in the current context (data types, json serialization) so this might be considered a bug.
 Watchout!
 
-This might change if use cases show that an error would be better.
+Use `#[ts(strict)]` (see below) to make them errors.
 
 If you reference another type in a struct e.g.
 
@@ -546,7 +611,16 @@ struct A {
 then this will "work" (producing `export type A = { x: number ,b: B<number> })`) but B will be opaque to
 typescript unless B is *also* `#[derive(TypescriptDefinition)]`.
 
-Currently there is no check for this omission.
+`TypescriptDefinition` doesn't check for this omission but `TypeScriptify` does: every type
+referred to by name (other than generic parameters) must implement `TypeScriptifyTrait` too, otherwise
+the build fails with ``B<f64>` has no typescript definition` pointing at the field. Fields with
+`#[ts(ts_type="...")]` or `#[ts(ts_as="...")]` aren't checked and `#[ts(unchecked)]` turns the check
+off for any other field, e.g. one whose type comes from another crate like `serde_json::Value`.
+(The custom error message uses `#[diagnostic::on_unimplemented]` so this crate needs rust 1.78 or later.)
+
+**This check is new since 0.1.10** and will break builds that `TypeScriptify` a type with a field
+from another crate (or any type without its own typescript definition). Derive `TypeScriptify` for
+that type or mark the field `#[ts(unchecked)]` (or `#[ts(ts_as="...")]`) to upgrade. See [CHANGELOG.md](CHANGELOG.md).
 
 ----
 
@@ -579,17 +653,53 @@ enum Color {
 ```
 
 because serde_json will render `Color::Red` as the string `"Red"` instead of `Color.Red` (because JSON).
+The keys are always the rust variant names while the values follow serde's renaming
+so `#[serde(rename_all="kebab-case")]` on `DarkRed` gives `DarkRed = "dark-red"`.
+
+If the enum is serialized as its discriminant (e.g. with [serde_repr](https://crates.io/crates/serde_repr)'s
+`#[derive(Serialize_repr)]`) add `#[ts(repr="number")]` and the rust discriminants are used
+and the type guard checks the numbers (the variants must all be unit variants and any
+discriminants integer literals, a `const` doesn't exist in typescript):
+
+```typescript
+enum Color {
+    Red = 1,
+    Green = 2,
+    Blue = 4
+}
+```
+
+A proc macro can't reliably see the other derives on a type so a `#[repr(u8)]` (or any integer) enum
+with discriminants is an error unless you say which it is: `#[ts(repr="number")]` or `#[ts(repr="string")]`.
+
+TS enums are nominal and don't play well with `isolatedModules`, so `#[ts(enum_style="union")]`
+gives `export type Color = "Red" | "Green" | "Blue";` instead (`enum_style="const_enum"` gives an
+`export const enum` and `enum_style="enum"` is the default). Add `#[ts(enum_values)]` to also get an array of
+all the values for iteration: `export const ColorValues = ["Red", "Green", "Blue"] as const;`
 
 Serde always seems to render `Result` (in json) as `{"Ok": T } | {"Err": E}` i.e as "External" so we do too.
 
 
-Formatting is rubbish and won't pass tslint. This is due to the quote! crate taking control of the output token stream. I don't know what it does with whitespace for example... (is whitespace a token in rust?). Anyhow... this crate applies a few band-aid regex patches to pretty things up. But use
+The output is built as a small typescript syntax tree and printed with a fixed layout
+(object types and enums on one line, one guard statement per line). You can change the layout
+with `#[ts(format="...")]` on a type or, for the whole crate, with the `TFY_FORMAT` environment
+variable e.g. `TFY_FORMAT="indent=4, multiline=3, separator=comma, trailing_comma, quote=single"`.
+The settings are:
+
+* `indent=N`: spaces per level (default 2).
+* `multiline=N`: put each field (or enum variant) on its own line when there are more than N of them.
+* `separator=semicolon|comma`: what goes between object fields (default semicolon).
+* `trailing_comma`: add a comma after the last one-per-line field or variant.
+* `quote=double|single`: the quotes used for strings (default double).
+
+`#[ts(format)]` settings override `TFY_FORMAT`. Cargo doesn't notice when `TFY_FORMAT`
+changes so you may need to `cargo clean -p yourcrate`. For anything fancier use
 [prettier](https://www.npmjs.com/package/prettier).
 
 
 We are not as clever as serde or the compiler in determining the actual type. For example this won't "work":
 
-```rust
+```rust,compile_fail
 use std::borrow::Cow as Pig;
 use typescript_definitions::{TypeScriptify,TypeScriptifyTrait};
 
@@ -600,14 +710,28 @@ struct S<'a> {
 println!("{}", S::type_script_ify());
 ```
 
-gives `export type S = { pig : Pig<string> }` instead of `export type S = { pig : string }`
+fails to compile since `Pig` has no typescript definition (with `#[ts(unchecked)]` it
+gives `export type S = { pig : Pig<string> }` instead of `export type S = { pig : string }`).
 Use `#[ts(ts_as="Cow")]` to fix this.
 
 At a certain point `typescript-definitions` just *assumes* that the token identifier `i32` (say) *is* really the rust signed 32 bit integer and not some crazy renamed struct in your code!
 
 Complex paths are ignored `std::borrow::Cow` and `mycrate::mod::Cow` are the same to us. We're not going to re-implement the compiler to find out if they are *actually* different. A Cow is always "Clone on write".
 
-We can't reasonably obey serde attributes like "flatten" since we would need to find the *actual* Struct object (from somewhere) and query its fields.
+`Self` however we do know about: `struct Tree<T> { value: T, children: Vec<Self> }` gives
+`export type Tree<T> = { value: T; children: Tree<T>[] }` and its type guard calls itself for the children.
+
+We don't look inside the types of `#[serde(flatten)]` fields, we just intersect with them. So `Base` had better
+have its own typescript definition (and type guard `isBase`).
+
+Types we can't map are quietly given a best guess: `any` for macros and `_`, an array for a raw
+pointer and a lambda for `fn(..)` and `Fn(..)` (which serde can't serialize anyway). With `#[ts(strict)]` on
+the type these are compile errors instead (fix them with `#[ts(ts_as="...")]` or
+`#[ts(ts_type="...")]`). The softer `#[ts(strict="unknown")]` gives them the typescript type `unknown`.
+Set `TFY_STRICT=true` (or `unknown`) in the environment to do this for the whole crate, `#[ts(strict=false)]` opts a type out.
+As with `TFY_FORMAT` cargo doesn't notice when `TFY_STRICT` changes so you may need to `cargo clean -p yourcrate`.
+Note that, as described in [Type Guards](#TypeGuards), `#[ts(strict)]` on the type (but not `strict="unknown"`
+or `TFY_STRICT`) also makes the guard reject undeclared keys unless you add `#[ts(deny_unknown=false)]`.
 
 
 ## <a name='Credits'></a>Credits
@@ -23,7 +23,7 @@ use std::borrow::Cow;
 pub use typescript_definitions_derive::*;
 
 // just for doc tests
-#[allow(unused, non_snake_case)]
+#[allow(unused, non_snake_case, clippy::all)]
 pub mod README;
 
 /// # Trait implemented by `TypeScriptify` derive macro.
@@ -32,6 +32,11 @@ pub mod README;
 /// or the [README](README/index.html).
 ///
 ///
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no typescript definition",
+    label = "`{Self}` doesn't derive `TypeScriptify`",
+    note = "derive `TypeScriptify` for `{Self}` or mark the field `#[ts(unchecked)]`"
+)]
 pub trait TypeScriptifyTrait {
     fn type_script_ify() -> Cow<'static, str>;
 
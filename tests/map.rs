@@ -38,9 +38,9 @@ fn untagged_enum() {
 
     assert_snapshot_matches!(
         Untagged::type_script_ify(),
-        @r###"export type Untagged = 
- | { id: number; attr: string } 
- | { id: number; attr2: string[] };"###
+        @r###"export type Untagged =
+  | { id: number; attr: string }
+  | { id: number; attr2: string[] };"###
 
     )
 }
@@ -56,10 +56,10 @@ fn external_enum() {
 
     assert_snapshot_matches!(
     External::type_script_ify(),
-        @r###"// Has documentation.
-export type External = 
- | { V1: { id: number; attr: string } } 
- | { V2: { id: number; attr2: string[] } };"###
+        @r###"/** Has documentation. */
+export type External =
+  | { V1: { id: number; attr: string } }
+  | { V2: { id: number; attr2: string[] } };"###
     )
 }
 
@@ -1,8 +1,8 @@
-#![allow(unused)]
+#![allow(unused, clippy::upper_case_acronyms)]
 
 use typescript_definitions::{TypeScriptify, TypeScriptifyTrait, TypescriptDefinition};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 // use serde::de::value::Error;
 
@@ -36,7 +36,7 @@ fn tuple_struct() {
 
     assert_snapshot_matches!(
         Tuple___typescript_definition(),
-        @"export type Tuple = [ number , string ];"
+        @"export type Tuple = [number, string];"
     )
 }
 #[cfg(feature = "test")]
@@ -101,7 +101,7 @@ fn struct_with_tuple() {
 
     assert_snapshot_matches!(
         Point2___typescript_definition(),
-        @"export type Point2 = { x: [ number , string , number[] ]; y: number; v: number[]; z: { [ key: string ]: number } };"
+        @"export type Point2 = { x: [number, string, number[]]; y: number; v: number[]; z: { [key: string]: number } };"
 
     )
 }
@@ -109,7 +109,7 @@ fn struct_with_tuple() {
 #[test]
 fn enum_with_renamed_newtype_variants() {
     #[derive(Serialize, TypescriptDefinition)]
-    #[serde(tag = "kind")]
+    #[serde(tag = "kind", content = "fields")]
     enum Enum {
         #[serde(rename = "Var1")]
         V1(bool),
@@ -123,10 +123,10 @@ fn enum_with_renamed_newtype_variants() {
 
     assert_snapshot_matches!(
         Enum___typescript_definition(),
-        @r###"export type Enum = 
- | { kind: "Var1"; fields: boolean } 
- | { kind: "Var2"; fields: number } 
- | { kind: "Var3"; fields: string };"###
+        @r###"export type Enum =
+  | { kind: "Var1"; fields: boolean }
+  | { kind: "Var2"; fields: number }
+  | { kind: "Var3"; fields: string };"###
 
     )
 }
@@ -142,7 +142,21 @@ fn enum_with_unit_variants() {
 
     assert_snapshot_matches!(
         Enum___typescript_definition(),
-        @r###"export enum Enum { V1 = "V1" , V2 = "V2" , V3 = "V3" };"###
+        @r###"export enum Enum { V1 = "V1", V2 = "V2", V3 = "V3" }"###
+    )
+}
+#[test]
+fn enum_with_renamed_unit_variants() {
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(rename_all = "kebab-case")]
+    enum Color {
+        DarkRed,
+        LightBlue,
+    }
+
+    assert_snapshot_matches!(
+        Color::type_script_ify(),
+        @r###"export enum Color { DarkRed = "dark-red", LightBlue = "light-blue" }"###
     )
 }
 #[cfg(feature = "test")]
@@ -158,10 +172,10 @@ fn enum_with_tuple_variants() {
 
     assert_snapshot_matches!(
     Enum___typescript_definition(),
-        @r###"export type Enum = 
- | { kind: "V1"; fields: [ number , string ] } 
- | { kind: "V2"; fields: [ number , boolean ] } 
- | { kind: "V3"; fields: [ number , number ] };"###
+        @r###"export type Enum =
+  | { kind: "V1"; fields: [number, string] }
+  | { kind: "V2"; fields: [number, boolean] }
+  | { kind: "V3"; fields: [number, number] };"###
     )
 }
 #[cfg(feature = "test")]
@@ -191,10 +205,10 @@ fn enum_with_struct_variants_and_renamed_fields() {
 
     assert_snapshot_matches!(
         Enum___typescript_definition(),
-        @r###"export type Enum = 
- | { kind: "V1"; Foo: boolean } 
- | { kind: "V2"; Bar: number; Baz: number } 
- | { kind: "V3"; Quux: string };"###
+        @r###"export type Enum =
+  | { kind: "V1"; Foo: boolean }
+  | { kind: "V2"; Bar: number; Baz: number }
+  | { kind: "V3"; Quux: string };"###
 
     )
 }
@@ -211,10 +225,10 @@ fn enum_with_struct_and_tags() {
 
     assert_snapshot_matches!(
         Enum___typescript_definition(),
-        @r###"export type Enum = 
- | { id: "V1"; content: { foo: boolean } } 
- | { id: "V2"; content: { bar: number; baz: number } } 
- | { id: "V3"; content: { quux: string } };"###
+        @r###"export type Enum =
+  | { id: "V1"; content: { foo: boolean } }
+  | { id: "V2"; content: { bar: number; baz: number } }
+  | { id: "V3"; content: { quux: string } };"###
 
     )
 }
@@ -260,6 +274,7 @@ fn cow_as_pig() {
 
     #[derive(TypeScriptify)]
     struct S<'a> {
+        #[ts(unchecked)]
         pig: Pig<'a, str>,
         cow: ::std::borrow::Cow<'a, str>,
     }
@@ -279,7 +294,7 @@ fn unit_enum_is_enum() {
     }
     assert_snapshot_matches!(
         Color::type_script_ify(),
-        @r###"export enum Color { Red = "Red" , Green = "Green" , Blue = "Blue" };"###
+        @r###"export enum Color { Red = "Red", Green = "Green", Blue = "Blue" }"###
 
     )
 }
@@ -291,11 +306,11 @@ fn struct_has_function() {
         key: i32,
         a: T,
         get: fn(arg: &i32) -> String,
-        get2: Fn(T, i32) -> Option<i32>,
+        get2: dyn Fn(T, i32) -> Option<i32>,
     }
     assert_snapshot_matches!(
         API::<i32>::type_script_ify(),
-        @"export type API<T> = { key: number; a: T; get: ( arg: number ) => string; get2: ( T , number ) => number | null };"
+        @"export type API<T> = { key: number; a: T; get: (arg: number) => string; get2: (_dummy0: T, _dummy1: number) => number | null };"
     )
 }
 
@@ -348,10 +363,10 @@ fn enum_with_serde_skip() {
     }
     assert_snapshot_matches!(
     S::type_script_ify(),
-        @r###"export type S = 
- | { kind: "A" } 
- | { kind: "E"; fields: { key: number; a: number } } 
- | { kind: "F"; fields: [ number , string ] };"###
+        @r###"export type S =
+  | { kind: "A" }
+  | { kind: "E"; fields: { key: number; a: number } }
+  | { kind: "F"; fields: [number, string] };"###
     )
 }
 
@@ -386,8 +401,10 @@ fn struct_with_pointers_and_slices() {
 
     assert_snapshot_matches!(
         Pointers___typescript_definition(),
-        @r###"// This is a doc comment
-// on multiple lines.
+        @r###"/**
+ * This is a doc comment
+ * on multiple lines.
+ */
 export type Pointers = { keys: string[]; buffer: number[]; buffer2: number[] };"###
 
     )
@@ -425,3 +442,353 @@ fn struct_with_two_fields_is_transparent_with_skip() {
 
     )
 }
+#[test]
+fn struct_with_flatten() {
+    #[derive(Serialize, TypeScriptify)]
+    struct Header {
+        id: i32,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    struct Message {
+        #[serde(flatten)]
+        header: Header,
+        body: String,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(tag = "kind")]
+    enum Event {
+        Open {
+            #[serde(flatten)]
+            header: Header,
+        },
+        Close {
+            #[serde(flatten)]
+            header: Header,
+            reason: String,
+        },
+    }
+    assert_snapshot_matches!(
+        Message::type_script_ify(),
+        @"export type Message = Header & { body: string };"
+    );
+    assert_snapshot_matches!(
+        Event::type_script_ify(),
+        @r###"export type Event =
+  | Header & { kind: "Open" }
+  | Header & { kind: "Close"; reason: string };"###
+    )
+}
+#[test]
+fn enum_with_internally_tagged_newtype_variants() {
+    #[derive(Serialize, TypeScriptify)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(tag = "kind")]
+    enum Shape {
+        Dot(Point),
+        Empty,
+    }
+    assert_snapshot_matches!(
+        Shape::type_script_ify(),
+        @r###"export type Shape =
+  | { kind: "Dot" } & Point
+  | { kind: "Empty" };"###
+    )
+}
+#[test]
+fn struct_with_tag() {
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(tag = "type", rename = "ping")]
+    struct Ping {
+        id: i32,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(tag = "type")]
+    struct Empty {}
+    assert_snapshot_matches!(
+        Ping::type_script_ify(),
+        @r###"export type Ping = { type: "ping"; id: number };"###
+    );
+    assert_snapshot_matches!(
+        Empty::type_script_ify(),
+        @r###"export type Empty = { type: "Empty" };"###
+    )
+}
+#[test]
+fn struct_with_proxy_types() {
+    #[derive(Serialize, Deserialize, TypeScriptify)]
+    struct Proxy {
+        value: String,
+    }
+    #[derive(Clone, Serialize, Deserialize, TypeScriptify)]
+    #[serde(into = "Proxy", from = "Proxy")]
+    struct Id {
+        value: u64,
+    }
+    impl From<Id> for Proxy {
+        fn from(id: Id) -> Proxy {
+            Proxy {
+                value: id.value.to_string(),
+            }
+        }
+    }
+    impl From<Proxy> for Id {
+        fn from(p: Proxy) -> Id {
+            Id {
+                value: p.value.parse().unwrap_or_default(),
+            }
+        }
+    }
+    #[derive(Clone, Serialize, Deserialize, TypeScriptify)]
+    #[serde(into = "String", try_from = "u32")]
+    #[ts(deserialize)]
+    enum Level {
+        Low,
+        High,
+    }
+    impl From<Level> for String {
+        fn from(l: Level) -> String {
+            match l {
+                Level::Low => "low".into(),
+                Level::High => "high".into(),
+            }
+        }
+    }
+    impl std::convert::TryFrom<u32> for Level {
+        type Error = String;
+        fn try_from(v: u32) -> Result<Level, String> {
+            match v {
+                0 => Ok(Level::Low),
+                1 => Ok(Level::High),
+                _ => Err(format!("bad level {}", v)),
+            }
+        }
+    }
+    assert_snapshot_matches!(
+        Id::type_script_ify(),
+        @"export type Id = Proxy;"
+    );
+    assert_snapshot_matches!(
+        Level::type_script_ify(),
+        @"export type Level = number;"
+    )
+}
+#[test]
+fn enum_with_other_and_untagged_variants() {
+    #[derive(Serialize, Deserialize, TypeScriptify)]
+    #[serde(tag = "kind", content = "data")]
+    enum Message {
+        Text(String),
+        #[serde(other)]
+        Unknown,
+    }
+    #[derive(Serialize, Deserialize, TypeScriptify)]
+    enum Value {
+        Number(f64),
+        #[serde(untagged)]
+        Text(String),
+    }
+    #[derive(Serialize, Deserialize, TypeScriptify)]
+    enum Color {
+        Red,
+        Green,
+        #[serde(other)]
+        Other,
+    }
+    assert_snapshot_matches!(
+        Message::type_script_ify(),
+        @r###"export type Message =
+  | { kind: "Text"; data: string }
+  | { kind: string };"###
+    );
+    assert_snapshot_matches!(
+        Value::type_script_ify(),
+        @r###"export type Value =
+  | { Number: number }
+  | string;"###
+    );
+    assert_snapshot_matches!(
+        Color::type_script_ify(),
+        @r###"export type Color = "Red" | "Green" | string;"###
+    )
+}
+#[test]
+fn enum_with_number_repr() {
+    #[derive(Serialize, TypeScriptify)]
+    #[repr(u8)]
+    #[ts(repr = "number")]
+    enum Level {
+        Low = 1,
+        Mid,
+        High = 10,
+    }
+    assert_snapshot_matches!(
+        Level::type_script_ify(),
+        @"export enum Level { Low = 1, Mid = 2, High = 10 }"
+    )
+}
+#[test]
+fn enum_styles() {
+    #[derive(Serialize, TypeScriptify)]
+    #[ts(enum_style = "const_enum")]
+    enum A {
+        X,
+        Y,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[ts(enum_values)]
+    enum B {
+        X,
+        Y,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[ts(enum_style = "union", repr = "number")]
+    enum C {
+        X = 1,
+        Y,
+    }
+    assert_snapshot_matches!(
+        A::type_script_ify(),
+        @r###"export const enum A { X = "X", Y = "Y" }"###
+    );
+    assert_snapshot_matches!(
+        B::type_script_ify(),
+        @r###"export enum B { X = "X", Y = "Y" }
+export const BValues = [B.X, B.Y] as const;"###
+    );
+    assert_snapshot_matches!(
+        C::type_script_ify(),
+        @"export type C = 1 | 2;"
+    )
+}
+#[test]
+fn struct_with_quoted_names() {
+    #[derive(Serialize, TypeScriptify)]
+    struct Headers {
+        #[serde(rename = "content-type")]
+        content_type: String,
+        #[serde(rename = "2fa")]
+        two_factor: bool,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(rename_all = "kebab-case")]
+    enum Event {
+        KeyDown { code: u32 },
+    }
+    assert_snapshot_matches!(
+        Headers::type_script_ify(),
+        @r###"export type Headers = { "content-type": string; "2fa": boolean };"###
+    );
+    assert_snapshot_matches!(
+        Event::type_script_ify(),
+        @r###"export type Event =
+  | { "key-down": { code: number } };"###
+    )
+}
+#[test]
+fn renamed_container() {
+    mod server {
+        use super::*;
+        #[derive(Serialize, TypeScriptify)]
+        #[ts(rename = "ServerConfig")]
+        pub struct Config {
+            pub port: u16,
+        }
+    }
+    mod client {
+        use super::*;
+        #[derive(Serialize, TypeScriptify)]
+        #[ts(rename = "ClientConfig")]
+        pub struct Config {
+            pub retries: u8,
+        }
+    }
+    #[derive(Serialize, TypeScriptify)]
+    struct App {
+        #[ts(ts_as = "ServerConfig")]
+        server: server::Config,
+        #[ts(ts_as = "ClientConfig")]
+        client: client::Config,
+    }
+    assert_snapshot_matches!(server::Config::type_script_ify(), @"export type ServerConfig = { port: number };");
+    assert_snapshot_matches!(client::Config::type_script_ify(), @"export type ClientConfig = { retries: number };");
+    assert_snapshot_matches!(App::type_script_ify(), @"export type App = { server: ServerConfig; client: ClientConfig };");
+}
+#[test]
+fn referenced_types_derive_too() {
+    mod m {
+        use super::*;
+        #[derive(Serialize, TypeScriptify)]
+        pub struct F {
+            pub f: f64,
+        }
+    }
+    #[derive(Serialize, TypeScriptify)]
+    struct B<T> {
+        q: T,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    struct A<'a, T> {
+        b: B<f64>,
+        c: Vec<B<&'a T>>,
+        f: Option<m::F>,
+        #[ts(unchecked)]
+        v: std::sync::Mutex<i32>,
+    }
+    assert_snapshot_matches!(A::<i32>::type_script_ify(), @"export type A<T> = { b: B<number>; c: B<T>[]; f: F | null; v: Mutex<number> };");
+}
+#[test]
+fn recursive_types() {
+    #[derive(Serialize, TypeScriptify)]
+    struct Tree<T> {
+        value: T,
+        children: Vec<Self>,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    enum List {
+        Cons(i32, Box<Self>),
+        Nil,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    struct Wrap<T> {
+        inner: T,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    struct Node {
+        kids: Vec<Wrap<Self>>,
+    }
+    assert_snapshot_matches!(Tree::<i32>::type_script_ify(), @"export type Tree<T> = { value: T; children: Tree<T>[] };");
+    assert_snapshot_matches!(Node::type_script_ify(), @"export type Node = { kids: Wrap<Node>[] };");
+    assert_snapshot_matches!(List::type_script_ify(), @r###"export type List =
+  | { Cons: [number, List] }
+  | "Nil";"###);
+}
+#[test]
+fn struct_with_optional_fields() {
+    #[derive(Serialize, TypeScriptify)]
+    struct S {
+        a: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        b: Option<String>,
+        #[serde(default)]
+        c: Vec<i32>,
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(default)]
+    struct D {
+        a: i32,
+        #[serde(skip_deserializing)]
+        b: i32,
+    }
+    assert_snapshot_matches!(
+        S::type_script_ify(),
+        @"export type S = { a: number; b?: string | null; c?: number[] };"
+    );
+    assert_snapshot_matches!(
+        D::type_script_ify(),
+        @"export type D = { a?: number; b: number };"
+    )
+}
@@ -1,5 +1,4 @@
 #![allow(unused)]
-use std::process;
 
 use typescript_definitions::{TypeScriptify, TypeScriptifyTrait, TypescriptDefinition};
 
@@ -8,32 +7,28 @@ use serde::Serialize;
 use insta::assert_snapshot_matches;
 use wasm_bindgen::prelude::*;
 
-use std::io::Write;
-use std::process::{Command, Stdio};
-pub fn prettier(s: &str) -> String {
-    let mut child = Command::new("prettier")
-        .arg("--parser")
-        .arg("typescript")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("failed to execute prettier");
+use std::collections::HashMap;
+use std::process::Command;
 
-    // hopefully the pipe buffers don't fill up :)
-    {
-        // limited borrow of stdin
-        let stdin = child.stdin.as_mut().expect("failed to get stdin");
-        stdin
-            .write_all(s.as_bytes())
-            .expect("failed to write to stdin");
-    }
-
-    let output = child
-        .wait_with_output()
-        .expect("failed to wait on prettier");
-
-    String::from_utf8_lossy(&output.stdout).trim().to_string()
+/// Run `guard` with node on each of the (JSON) `values`, `None` if there is no node.
+pub fn run_guard(guard: &str, values: &[&str]) -> Option<Vec<bool>> {
+    // node doesn't understand the `(obj: any): obj is T` annotations so drop them
+    let body = &guard[guard.find("=> {")?..];
+    let script = format!(
+        "const guard = (obj) {}\nfor (const v of [{}]) console.log(guard(v));",
+        body,
+        values.join(", ")
+    );
+    let output = Command::new("node").arg("-e").arg(&script).output().ok()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(stdout.lines().map(|l| l == "true").collect())
 }
+
 #[cfg(feature = "type-guards")]
 #[test]
 fn verify_untagged_enum() {
@@ -45,45 +40,39 @@ fn verify_untagged_enum() {
         V1 { id: i32, attr: String },
         V2 { id: i32, attr2: Vec<String> },
     }
-    let verify_untagged_enum = prettier(&Untagged::type_script_guard().unwrap());
+    let verify_untagged_enum = Untagged::type_script_guard().unwrap();
     assert_snapshot_matches!(verify_untagged_enum,
         @r###"export const isUntagged = (obj: any): obj is Untagged => {
   if (obj == undefined) return false;
-  if (
-    (() => {
-      if (obj.id === undefined) return false;
-      {
-        const val = obj.id;
-        if (!(typeof val === "number")) return false;
-      }
-      if (obj.attr === undefined) return false;
-      {
-        const val = obj.attr;
-        if (!(typeof val === "string")) return false;
-      }
-      return true;
-    })()
-  )
+  if ((() => {
+    if (obj.id === undefined) return false;
+    {
+      const val = obj.id;
+      if (!(typeof val === "number")) return false;
+    }
+    if (obj.attr === undefined) return false;
+    {
+      const val = obj.attr;
+      if (!(typeof val === "string")) return false;
+    }
     return true;
-  if (
-    (() => {
-      if (obj.id === undefined) return false;
-      {
-        const val = obj.id;
-        if (!(typeof val === "number")) return false;
-      }
-      if (obj.attr2 === undefined) return false;
-      {
-        const val = obj.attr2;
-        if (!Array.isArray(val)) return false;
-        for (let x of val) {
-          if (!(typeof x === "string")) return false;
-        }
+  })()) return true;
+  if ((() => {
+    if (obj.id === undefined) return false;
+    {
+      const val = obj.id;
+      if (!(typeof val === "number")) return false;
+    }
+    if (obj.attr2 === undefined) return false;
+    {
+      const val = obj.attr2;
+      if (!Array.isArray(val)) return false;
+      for (let x of val) {
+        if (!(typeof x === "string")) return false;
       }
-      return true;
-    })()
-  )
+    }
     return true;
+  })()) return true;
   return false;
 };"###
     )
@@ -99,7 +88,7 @@ fn verify_first_only() {
         vals: Vec<String>,
     }
 
-    let verify_first_only = prettier(&S::type_script_guard().unwrap());
+    let verify_first_only = S::type_script_guard().unwrap();
     assert_snapshot_matches!(verify_first_only,
     @r###"export const isS = (obj: any): obj is S => {
   if (obj == undefined) return false;
@@ -141,7 +130,7 @@ fn verify_typescript_enum() {
         Green,
         Blue,
     }
-    let verify_typescript_enum = prettier(&TyEnum::type_script_guard().unwrap());
+    let verify_typescript_enum = TyEnum::type_script_guard().unwrap();
     assert_snapshot_matches!(verify_typescript_enum,
         @r###"export const isTyEnum = (obj: any): obj is TyEnum => {
   if (!(obj === "Red" || obj === "Green" || obj === "Blue")) return false;
@@ -159,7 +148,7 @@ fn verify_ts_as() {
     }
 
     assert_snapshot_matches!(
-        prettier(&Sub::type_script_guard().unwrap()),
+        Sub::type_script_guard().unwrap(),
         @r###"export const isSub = (obj: any): obj is Sub => {
   if (obj == undefined) return false;
   if (obj.b === undefined) return false;
@@ -185,7 +174,7 @@ fn verify_option() {
     }
 
     assert_snapshot_matches!(
-    prettier(&Maybe::type_script_guard().unwrap()),
+    Maybe::type_script_guard().unwrap(),
         @r###"export const isMaybe = (obj: any): obj is Maybe => {
   if (obj == undefined) return false;
   if (obj.maybe === undefined) return false;
@@ -199,3 +188,40 @@ fn verify_option() {
 };"###
     );
 }
+#[cfg(feature = "type-guards")]
+#[test]
+fn run_flatten_map_in_enum_variant() {
+    #[derive(Serialize, TypeScriptify)]
+    enum External {
+        V {
+            id: u32,
+            #[serde(flatten)]
+            extra: HashMap<String, u32>,
+        },
+    }
+    #[derive(Serialize, TypeScriptify)]
+    #[serde(tag = "kind", content = "data")]
+    enum Adjacent {
+        V {
+            id: u32,
+            #[serde(flatten)]
+            extra: HashMap<String, u32>,
+        },
+    }
+    let values = [
+        r#"{ "V": { "id": 1 } }"#,
+        r#"{ "V": { "id": 1, "x": 2 } }"#,
+        r#"{ "V": { "id": 1, "x": "2" } }"#,
+    ];
+    if let Some(ok) = run_guard(&External::type_script_guard().unwrap(), &values) {
+        assert_eq!(ok, [true, true, false]);
+    }
+    let values = [
+        r#"{ "kind": "V", "data": { "id": 1 } }"#,
+        r#"{ "kind": "V", "data": { "id": 1, "x": 2 } }"#,
+        r#"{ "kind": "V", "data": { "id": 1, "x": "2" } }"#,
+    ];
+    if let Some(ok) = run_guard(&Adjacent::type_script_guard().unwrap(), &values) {
+        assert_eq!(ok, [true, true, false]);
+    }
+}
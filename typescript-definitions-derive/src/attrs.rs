@@ -6,19 +6,64 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use super::printer::Format;
 use super::{ast, ident_from_str, Ctxt};
-use quote::quote;
+use quote::{quote, ToTokens};
 
 use proc_macro2::TokenStream;
-use syn::{Attribute, Ident, Lit, Meta, /* MetaList,*/ MetaNameValue, NestedMeta};
+use syn::{Attribute, Ident, Lit, LitStr, Meta, /* MetaList,*/ MetaNameValue, NestedMeta};
+
+/// how an enum of unit variants is rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnumStyle {
+    Enum,      // export enum X { A = "A" }
+    ConstEnum, // export const enum X { A = "A" }
+    Union,     // export type X = "A" | "B"
+}
+
+/// what to do with a rust type that has no typescript equivalent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strict {
+    Off,     // map it to `any` (or a best guess)
+    Unknown, // map it to `unknown`
+    Deny,    // compile error
+}
+
+impl Strict {
+    /// from `#[ts(strict = "...")]` or `TFY_STRICT`
+    pub fn from_str(s: &str) -> Option<Strict> {
+        match s {
+            "true" | "deny" => Some(Strict::Deny),
+            "unknown" => Some(Strict::Unknown),
+            "false" => Some(Strict::Off),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Attrs {
-    pub comments: Vec<String>,
+    pub doc: Option<String>,
     pub guard: bool,
+    pub strict: Strict,
+    /// an explicit `#[ts(strict)]` (not `TFY_STRICT`) also rejects undeclared keys...
+    pub strict_keys: bool,
+    /// ... unless `#[ts(deny_unknown=false)]` says otherwise
+    pub deny_unknown: Option<bool>,
+    pub deserialize: bool,
+    /// `#[ts(repr = "number")]` is `Some(true)`; None if not given
+    pub number_repr: Option<bool>,
+    pub enum_style: EnumStyle,
+    pub enum_values: bool,
+    pub format: Format,
     pub only_first: bool,
-    pub ts_type: Option<String>,
-    pub ts_guard: Option<String>,
+    pub skip: bool,
+    pub rename: Option<String>,
+    pub optional: bool,
+    pub readonly: bool,
+    pub unchecked: bool,
+    pub ts_type: Option<LitStr>,
+    pub ts_guard: Option<LitStr>,
     pub ts_as: Option<syn::Type>,
 }
 
@@ -38,98 +83,286 @@ pub fn turbofish_check(v: &str) -> Result<TokenStream, String> {
         },
     }
 }
+/// Remove `#[serde(key...)]` from `attrs` returning it.
+/// Used for attributes serde_derive_internals 0.24 doesn't know about
+/// (but serde itself does) so that it doesn't reject the input.
+fn take_serde(attrs: &mut [Attribute], key: &str) -> Option<Meta> {
+    use syn::Meta::*;
+    use NestedMeta::*;
+    let mut ret = None;
+    for attr in attrs.iter_mut() {
+        if path_to_str(&attr.path) != "serde" {
+            continue;
+        }
+        let nested = match attr.parse_meta() {
+            Ok(List(l)) => l.nested,
+            _ => continue,
+        };
+        let mut rest = vec![];
+        for m in nested {
+            match m {
+                Meta(m) if m.name() == key => ret = Some(m),
+                m => rest.push(m),
+            }
+        }
+        attr.tts = quote!(( #(#rest),* ));
+    }
+    ret
+}
+
+fn take_serde_str(attrs: &mut [Attribute], key: &str) -> Option<String> {
+    match take_serde(attrs, key)? {
+        Meta::NameValue(MetaNameValue {
+            lit: Lit::Str(ref value),
+            ..
+        }) => Some(value.value()),
+        _ => None,
+    }
+}
+
+/// `#[serde(tag = "...")]` on a struct with named fields.
+pub fn take_struct_tag(input: &mut syn::DeriveInput) -> Option<String> {
+    match input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(_),
+            ..
+        }) => take_serde_str(&mut input.attrs, "tag"),
+        _ => None,
+    }
+}
+
+/// `#[serde(try_from = "Type")]` on a container.
+pub fn take_try_from(input: &mut syn::DeriveInput, ctxt: &Ctxt) -> Option<syn::Type> {
+    let v = take_serde_str(&mut input.attrs, "try_from")?;
+    match syn::parse_str::<syn::Type>(&v) {
+        Ok(t) => Some(t),
+        Err(..) => {
+            ctxt.error_spanned_by(
+                &input.ident,
+                format!("try_from: \"{}\" is not a valid rust type", v),
+            );
+            None
+        }
+    }
+}
+/// The JSDoc text: the docs followed by `@deprecated`, `@example` and
+/// `@see` tags from `#[deprecated]`, `#[ts(example = "...")]` and
+/// `#[ts(see = "...")]`.
+pub fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let mut lines = markdown(attrs).into_iter().collect::<Vec<_>>();
+    lines.extend(deprecated(attrs));
+    for (tag, value) in jsdoc_tags(attrs) {
+        if tag == "example" {
+            // the example is everything up to the next tag
+            lines.push("@example".to_string());
+            lines.push(value);
+        } else {
+            lines.push(format!("@{} {}", tag, value));
+        }
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// `@deprecated since 1.2: note` from `#[deprecated(since = "1.2", note = "note")]`
+fn deprecated(attrs: &[Attribute]) -> Option<String> {
+    use syn::Meta::*;
+    let attr = attrs
+        .iter()
+        .find(|attr| path_to_str(&attr.path) == "deprecated")?;
+    let (since, note) = match attr.parse_meta() {
+        Ok(NameValue(MetaNameValue {
+            lit: Lit::Str(s), ..
+        })) => (None, Some(s.value())),
+        Ok(List(l)) => {
+            let value = |key: &str| {
+                l.nested.iter().find_map(|m| match m {
+                    NestedMeta::Meta(NameValue(MetaNameValue {
+                        ident,
+                        lit: Lit::Str(s),
+                        ..
+                    })) if ident == key => Some(s.value()),
+                    _ => None,
+                })
+            };
+            (value("since"), value("note"))
+        }
+        _ => (None, None),
+    };
+    Some(match (since, note) {
+        (Some(since), Some(note)) => format!("@deprecated since {}: {}", since, note),
+        (Some(since), None) => format!("@deprecated since {}", since),
+        (None, Some(note)) => format!("@deprecated {}", note),
+        (None, None) => "@deprecated".to_string(),
+    })
+}
+
+/// `#[ts(example = "...")]` and `#[ts(see = "...")]` in order. Any errors
+/// are reported when the rest of the `#[ts(...)]` is parsed.
+fn jsdoc_tags(attrs: &[Attribute]) -> Vec<(String, String)> {
+    use syn::Meta::*;
+    attrs
+        .iter()
+        .filter(|attr| path_to_str(&attr.path) == "ts")
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(List(l)) => Some(l.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|m| match m {
+            NestedMeta::Meta(NameValue(MetaNameValue {
+                ident,
+                lit: Lit::Str(s),
+                ..
+            })) if ident == "example" || ident == "see" => Some((ident.to_string(), s.value())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The `///` (or `/** */`) docs as markdown: blank lines and the
+/// indentation of code blocks are kept but the common leading
+/// whitespace is removed.
+fn markdown(attrs: &[Attribute]) -> Option<String> {
+    use syn::Meta::*;
+    let text = attrs
+        .iter()
+        .filter(|attr| path_to_str(&attr.path) == "doc")
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(NameValue(MetaNameValue {
+                lit: Lit::Str(s), ..
+            })) => Some(s.value()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+    let indent = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        // only (single byte) spaces and tabs so we can slice at `indent`
+        .map(|l| l.len() - l.trim_start_matches([' ', '\t']).len())
+        .min()?;
+    let lines = lines
+        .iter()
+        .map(|l| if l.is_empty() { l } else { &l[indent..] })
+        .skip_while(|l| l.is_empty())
+        .collect::<Vec<_>>();
+    let end = lines.iter().rposition(|l| !l.is_empty())? + 1;
+    Some(lines[..end].join("\n"))
+}
+
+/// the docs and `#[ts(...)]` attributes of each enum variant.
+pub fn variant_attrs(input: &syn::DeriveInput, ctxt: &Ctxt) -> Vec<(String, Attrs)> {
+    match input.data {
+        syn::Data::Enum(ref e) => e
+            .variants
+            .iter()
+            .map(|v| {
+                let mut attrs = Attrs::new();
+                attrs.push_doc_comment(&v.attrs);
+                attrs.push_variant_attrs(&v.ident, &v.attrs, ctxt);
+                (v.ident.to_string(), attrs)
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// field attributes keyed by the address of the `syn::Field`
+/// (serde's `ast::Field` has no id).
+pub type FieldAttrs = Vec<(*const syn::Field, Attrs)>;
+
+/// `#[ts(...)]` on every field of the container (including those of its variants).
+pub fn field_attrs(data: &ast::Data, ctxt: &Ctxt) -> FieldAttrs {
+    let fields: Vec<&ast::Field> = match data {
+        ast::Data::Enum(variants) => variants.iter().flat_map(|v| &v.fields).collect(),
+        ast::Data::Struct(_, fields) => fields.iter().collect(),
+    };
+    fields
+        .into_iter()
+        .map(|f| (f.original as *const syn::Field, Attrs::from_field(f, ctxt)))
+        .collect()
+}
+
+/// `#[repr(u8)]` (or any other integer): a C-like enum that may well be
+/// serialized as its discriminants (e.g. with serde_repr) but we can't tell.
+pub fn int_repr(attrs: &[Attribute]) -> Option<&Attribute> {
+    use syn::Meta::*;
+    use NestedMeta::*;
+    const INTS: &[&str] = &[
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+    attrs
+        .iter()
+        .filter(|attr| path_to_str(&attr.path) == "repr")
+        .find(|attr| match attr.parse_meta() {
+            Ok(List(l)) => l.nested.iter().any(|m| match m {
+                Meta(Word(ref w)) => INTS.iter().any(|i| w == i),
+                _ => false,
+            }),
+            _ => false,
+        })
+}
+
+/// variants marked `#[serde(untagged)]` in an otherwise tagged enum.
+pub fn take_untagged_variants(input: &mut syn::DeriveInput) -> Vec<String> {
+    match input.data {
+        syn::Data::Enum(ref mut e) => e
+            .variants
+            .iter_mut()
+            .filter_map(|v| match take_serde(&mut v.attrs, "untagged") {
+                Some(Meta::Word(..)) => Some(v.ident.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
 impl Attrs {
     pub fn new() -> Attrs {
         Attrs {
-            comments: vec![],
+            doc: None,
             // turbofish: None,
             guard: true,
+            strict: Strict::Off,
+            strict_keys: false,
+            deny_unknown: None,
+            deserialize: false,
+            number_repr: None,
+            enum_style: EnumStyle::Enum,
+            enum_values: false,
+            format: Format::default(),
             only_first: false,
+            skip: false,
+            rename: None,
+            optional: false,
+            readonly: false,
+            unchecked: false,
             ts_type: None,
             ts_guard: None,
-            ts_as : None
-            // isa: HashMap::new(),
+            ts_as: None, // isa: HashMap::new(),
         }
     }
     pub fn push_doc_comment(&mut self, attrs: &[Attribute]) {
-        let doc_comments = attrs
-            .iter()
-            .filter_map(|attr| {
-                if path_to_str(&attr.path) == "doc" {
-                    attr.parse_meta().ok()
-                } else {
-                    None
-                }
-            })
-            .filter_map(|attr| {
-                use Lit::*;
-                use Meta::*;
-                if let NameValue(MetaNameValue {
-                    ident, lit: Str(s), ..
-                }) = attr
-                {
-                    if ident != "doc" {
-                        return None;
-                    }
-                    let value = s.value();
-                    let text = value
-                        .trim_start_matches("//!")
-                        .trim_start_matches("///")
-                        .trim_start_matches("/*!")
-                        .trim_start_matches("/**")
-                        .trim_end_matches("*/")
-                        .trim();
-                    if text.is_empty() {
-                        None
-                    } else {
-                        Some(text.to_string())
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-        if doc_comments.is_empty() {
-            return;
-        }
-
-        let merged_lines = doc_comments
-            .iter()
-            .map(|s| format!("// {}", s))
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        self.comments.push(merged_lines);
+        self.doc = doc_comment(attrs);
     }
-
-    pub fn to_comment_str(&self) -> String {
-        if self.comments.is_empty() {
-            String::default()
-        } else {
-            self.comments.join("\n") + "\n" // <-- need better way!
-        }
-    }
-    fn err_msg<'a>(&self, msg: String, ctxt: Option<&'a Ctxt>) {
-        if let Some(ctxt) = ctxt {
-            ctxt.error(msg);
-        } else {
-            panic!(msg)
-        };
+    fn err_msg<A: ToTokens>(&self, tokens: A, msg: String, ctxt: &Ctxt) {
+        ctxt.error_spanned_by(tokens, msg);
     }
     pub fn find_typescript<'a>(
         attrs: &'a [Attribute],
-        ctxt: Option<&'a Ctxt>,
+        ctxt: &'a Ctxt,
     ) -> impl Iterator<Item = Meta> + 'a {
         use syn::Meta::*;
         use NestedMeta::*;
 
-        fn err(msg: String, ctxt: Option<&'_ Ctxt>) {
-            if let Some(ctxt) = ctxt {
-                ctxt.error(format!("invalid typescript syntax: {}", msg));
-            } else {
-                panic!("invalid typescript syntax: {}", msg)
-            };
+        fn err<A: ToTokens>(tokens: A, msg: String, ctxt: &Ctxt) {
+            ctxt.error_spanned_by(tokens, format!("invalid typescript syntax: {}", msg));
         }
 
         attrs
@@ -138,7 +371,7 @@ impl Attrs {
                 "ts" => match attr.parse_meta() {
                     Ok(v) => Some(v),
                     Err(msg) => {
-                        err(msg.to_string(), ctxt);
+                        err(attr, msg.to_string(), ctxt);
                         None
                     }
                 },
@@ -147,7 +380,7 @@ impl Attrs {
             .filter_map(move |m| match m {
                 List(l) => Some(l.nested),
                 ref tokens => {
-                    err(quote!(#tokens).to_string(), ctxt);
+                    err(tokens, quote!(#tokens).to_string(), ctxt);
                     None
                 }
             })
@@ -155,17 +388,17 @@ impl Attrs {
             .filter_map(move |m| match m {
                 Meta(m) => Some(m),
                 ref tokens => {
-                    err(quote!(#tokens).to_string(), ctxt);
+                    err(tokens, quote!(#tokens).to_string(), ctxt);
                     None
                 }
             })
     }
-    pub fn push_attrs(&mut self, struct_ident: &Ident, attrs: &[Attribute], ctxt: Option<&Ctxt>) {
+    pub fn push_attrs(&mut self, struct_ident: &Ident, attrs: &[Attribute], ctxt: &Ctxt) {
         use syn::Meta::*;
         use Lit::*;
         // use NestedMeta::*;
 
-        for attr in Self::find_typescript(&attrs, ctxt) {
+        for attr in Self::find_typescript(attrs, ctxt) {
             match attr {
                 NameValue(MetaNameValue {
                     ref ident,
@@ -183,6 +416,7 @@ impl Attrs {
                         Ok(v) => v,
                         Err(..) => {
                             self.err_msg(
+                                &attr,
                                 format!(
                                     "{}: guard must be true or false not \"{}\"",
                                     struct_ident,
@@ -195,6 +429,82 @@ impl Attrs {
                     }
                 }
                 Word(ref w) if w == "guard" => self.guard = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "strict" => {
+                    self.strict = if value.value {
+                        Strict::Deny
+                    } else {
+                        Strict::Off
+                    };
+                    self.strict_keys = value.value;
+                }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "strict" => {
+                    self.strict = match Strict::from_str(&value.value()) {
+                        Some(strict) => strict,
+                        None => {
+                            self.err_msg(
+                                &attr,
+                                format!(
+                                    r#"{}: strict value must be true, false or "unknown" not {}"#,
+                                    struct_ident,
+                                    quote!(#value)
+                                ),
+                                ctxt,
+                            );
+                            Strict::Off
+                        }
+                    };
+                    self.strict_keys = self.strict == Strict::Deny;
+                }
+                Word(ref w) if w == "strict" => {
+                    self.strict = Strict::Deny;
+                    self.strict_keys = true;
+                }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "deny_unknown" => {
+                    self.deny_unknown = Some(value.value);
+                }
+                Word(ref w) if w == "deny_unknown" => self.deny_unknown = Some(true),
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "deserialize" => {
+                    self.deserialize = value.value;
+                }
+                Word(ref w) if w == "deserialize" => self.deserialize = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "repr" => {
+                    self.number_repr = match value.value().as_ref() {
+                        "number" => Some(true),
+                        "string" => Some(false),
+                        _ => {
+                            self.err_msg(
+                                &attr,
+                                format!(
+                                    r#"{}: repr value must be "number" or "string" not "{}""#,
+                                    struct_ident,
+                                    quote!(#value)
+                                ),
+                                ctxt,
+                            );
+                            Some(false)
+                        }
+                    }
+                }
                 // List(MetaList {
                 //     ref ident,
                 //     ref nested,
@@ -227,40 +537,146 @@ impl Attrs {
                 //         Ok(tokens) => self.turbofish = Some(tokens),
                 //     }
                 // }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "enum_style" => {
+                    self.enum_style = match value.value().as_ref() {
+                        "enum" => EnumStyle::Enum,
+                        "const_enum" => EnumStyle::ConstEnum,
+                        "union" => EnumStyle::Union,
+                        _ => {
+                            self.err_msg(&attr, format!(
+                                    r#"{}: enum_style value must be "enum", "const_enum" or "union" not "{}""#,
+                                    struct_ident,
+                                    quote!(#value)
+                                ),
+                                ctxt,
+                            );
+                            EnumStyle::Enum
+                        }
+                    }
+                }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "enum_values" => {
+                    self.enum_values = value.value;
+                }
+                Word(ref w) if w == "enum_values" => self.enum_values = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "rename" => {
+                    let v = value.value();
+                    match syn::parse_str::<Ident>(&v) {
+                        Ok(..) => self.rename = Some(v),
+                        Err(..) => self.err_msg(
+                            &attr,
+                            format!(
+                                r#"{}: rename "{}" is not a valid identifier"#,
+                                struct_ident, v
+                            ),
+                            ctxt,
+                        ),
+                    }
+                }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "format" => {
+                    if let Err(msg) = self.format.update(&value.value()) {
+                        self.err_msg(&attr, format!("{}: {}", struct_ident, msg), ctxt);
+                    }
+                }
+                // read by doc_comment
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(..),
+                    ..
+                }) if ident == "example" || ident == "see" => {}
                 ref i @ NameValue(..) | ref i @ List(..) | ref i @ Word(..) => {
-                    self.err_msg(format!("unsupported option: {}", quote!(#i)), ctxt);
+                    self.err_msg(&attr, format!("unsupported option: {}", quote!(#i)), ctxt);
                 }
             }
         }
     }
-    pub fn push_field_attrs(
-        &mut self,
-        struct_ident: &Ident,
-        attrs: &[Attribute],
-        ctxt: Option<&Ctxt>,
-    ) {
+    pub fn push_variant_attrs(&mut self, variant_ident: &Ident, attrs: &[Attribute], ctxt: &Ctxt) {
         use syn::Meta::*;
         use Lit::*;
-        // use NestedMeta::*;
 
-        for attr in Self::find_typescript(&attrs, ctxt) {
+        for attr in Self::find_typescript(attrs, ctxt) {
             match attr {
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "skip" => {
+                    self.skip = value.value;
+                }
+                Word(ref w) if w == "skip" => self.skip = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "rename" => {
+                    self.rename = Some(value.value());
+                }
                 NameValue(MetaNameValue {
                     ref ident,
                     lit: Str(ref value),
                     ..
                 }) if ident == "ts_type" => {
-                    let v = value.value();
+                    self.ts_type = Some(value.clone());
+                }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "guard" => {
+                    self.guard = value.value;
+                }
+                Word(ref w) if w == "guard" => self.guard = true,
+                // read by doc_comment
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(..),
+                    ..
+                }) if ident == "example" || ident == "see" => {}
+                ref i @ NameValue(..) | ref i @ List(..) | ref i @ Word(..) => {
+                    self.err_msg(
+                        &attr,
+                        format!("{}: unsupported option: {}", variant_ident, quote!(#i)),
+                        ctxt,
+                    );
+                }
+            }
+        }
+    }
+    pub fn push_field_attrs(&mut self, struct_ident: &Ident, attrs: &[Attribute], ctxt: &Ctxt) {
+        use syn::Meta::*;
+        use Lit::*;
+        // use NestedMeta::*;
 
-                    self.ts_type = Some(v);
+        for attr in Self::find_typescript(attrs, ctxt) {
+            match attr {
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "ts_type" => {
+                    self.ts_type = Some(value.clone());
                 }
                 NameValue(MetaNameValue {
                     ref ident,
                     lit: Str(ref value),
                     ..
                 }) if ident == "ts_guard" => {
-                    let v = value.value();
-                    self.ts_guard = Some(v);
+                    self.ts_guard = Some(value.clone());
                 }
                 NameValue(MetaNameValue {
                     ref ident,
@@ -272,6 +688,7 @@ impl Attrs {
                         Ok(t) => self.ts_as = Some(t),
                         Err(..) => {
                             self.err_msg(
+                                &attr,
                                 format!("ts_as: \"{}\" is not a valid rust type", v),
                                 ctxt,
                             );
@@ -284,37 +701,89 @@ impl Attrs {
                     lit: Str(ref value),
                     ..
                 }) if ident == "array_check" => {
-                    self.only_first = match value.value().as_ref() {
-                        "first" => true,
-                        "all" => false,
-                        _ => {
-                            self.err_msg(
-                                format!(
+                    self.only_first =
+                        match value.value().as_ref() {
+                            "first" => true,
+                            "all" => false,
+                            _ => {
+                                self.err_msg(&attr, format!(
                                     r#"{}: array_check value must be "first" or "all" not "{}""#,
                                     struct_ident,
                                     quote!(#value)
                                 ),
                                 ctxt,
                             );
-                            false
+                                false
+                            }
                         }
-                    }
                 }
                 Word(ref w) if w == "array_check" => self.only_first = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "skip" => {
+                    self.skip = value.value;
+                }
+                Word(ref w) if w == "skip" => self.skip = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(ref value),
+                    ..
+                }) if ident == "rename" => {
+                    self.rename = Some(value.value());
+                }
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "optional" => {
+                    self.optional = value.value;
+                }
+                Word(ref w) if w == "optional" => self.optional = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "readonly" => {
+                    self.readonly = value.value;
+                }
+                Word(ref w) if w == "readonly" => self.readonly = true,
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Bool(ref value),
+                    ..
+                }) if ident == "unchecked" => {
+                    self.unchecked = value.value;
+                }
+                Word(ref w) if w == "unchecked" => self.unchecked = true,
 
+                // read by doc_comment
+                NameValue(MetaNameValue {
+                    ref ident,
+                    lit: Str(..),
+                    ..
+                }) if ident == "example" || ident == "see" => {}
                 ref i @ NameValue(..) | ref i @ List(..) | ref i @ Word(..) => {
-                    self.err_msg(format!("unsupported option: {}", quote!(#i)), ctxt);
+                    self.err_msg(&attr, format!("unsupported option: {}", quote!(#i)), ctxt);
                 }
             }
         }
     }
-    pub fn from_field(field: &ast::Field, ctxt: Option<&Ctxt>) -> Attrs {
+    pub fn from_field(field: &ast::Field, ctxt: &Ctxt) -> Attrs {
         let mut res = Self::new();
         if let Some(ref ident) = field.original.ident {
             res.push_field_attrs(ident, &field.original.attrs, ctxt);
         } else {
             let id = ident_from_str("unnamed");
             res.push_field_attrs(&id, &field.original.attrs, ctxt);
+            if res.skip || res.rename.is_some() || res.optional || res.readonly {
+                res.err_msg(
+                    field.original,
+                    "skip, rename, optional and readonly need a named field".to_string(),
+                    ctxt,
+                );
+            }
         }
         res
     }
@@ -0,0 +1,57 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Errors are collected while parsing (rather than panicking at the
+//! first one) and then reported together as `compile_error!`s, each
+//! pointing at the field or `#[ts(...)]` attribute at fault.
+
+use super::QuoteT;
+use proc_macro2::Span;
+use quote::ToTokens;
+use std::cell::RefCell;
+use std::fmt::Display;
+
+#[derive(Default)]
+pub struct Ctxt {
+    errors: RefCell<Vec<syn::Error>>,
+}
+
+impl Ctxt {
+    pub fn new() -> Self {
+        Ctxt::default()
+    }
+
+    /// an error pointing at `tokens`
+    pub fn error_spanned_by<A: ToTokens, T: Display>(&self, tokens: A, msg: T) {
+        self.push(syn::Error::new_spanned(tokens.into_token_stream(), msg))
+    }
+
+    /// an error at `span`
+    pub fn error_at<T: Display>(&self, span: Span, msg: T) {
+        self.push(syn::Error::new(span, msg))
+    }
+
+    fn push(&self, err: syn::Error) {
+        self.errors.borrow_mut().push(err)
+    }
+
+    /// take the errors (if any) collected so far
+    pub fn check(&self) -> Result<(), Vec<syn::Error>> {
+        let errors = self.errors.replace(vec![]);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// `compile_error!("...")` for each error
+pub fn to_compile_errors(errors: &[syn::Error]) -> QuoteT {
+    errors.iter().map(syn::Error::to_compile_error).collect()
+}
@@ -5,11 +5,11 @@
 // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use super::patch::{eq, nl};
-use super::{filter_visible, ident_from_str, ParseContext, QuoteMaker};
-use proc_macro2::Literal;
-use quote::quote;
+use super::attrs::Attrs;
+use super::tsast::{Expr, Lit, Member, Prop, Stmt, Ty};
+use super::{filter_visible, split_flatten, unraw, EnumStyle, ParseContext, QuoteMaker, QuoteT};
 use serde_derive_internals::{ast, ast::Variant, attr::EnumTag};
+use syn::LitStr;
 const CONTENT: &str = "fields"; // default content tag
                                 // const TAG: &'static str = "kind"; // default tag tag
 
@@ -18,7 +18,7 @@ struct TagInfo<'a> {
     content: Option<&'a str>,
     untagged: bool,
 }
-impl<'a> ParseContext<'_> {
+impl<'a> ParseContext {
     pub(crate) fn derive_enum(
         &self,
         variants: &[ast::Variant<'a>],
@@ -32,7 +32,7 @@ impl<'a> ParseContext<'_> {
             },
             EnumTag::Adjacent { tag, content, .. } => TagInfo {
                 tag: Some(tag),
-                content: Some(&content),
+                content: Some(content),
                 untagged: false,
             },
             EnumTag::External => TagInfo {
@@ -47,10 +47,16 @@ impl<'a> ParseContext<'_> {
             },
         };
 
-        // check for #[serde(skip)]
+        if !self.discriminants.is_empty()
+            && variants.iter().all(|v| matches!(v.style, ast::Style::Unit))
+        {
+            return self.derive_number_enum(variants);
+        }
+
+        // check for #[serde(skip)] and #[ts(skip)]
         let mut skip_variants: Vec<&ast::Variant<'a>> = Vec::with_capacity(variants.len());
         for v in variants {
-            if v.attrs.skip_serializing() {
+            if v.attrs.skip_serializing() || self.is_skipped(v) {
                 continue;
             }
             skip_variants.push(v);
@@ -59,7 +65,11 @@ impl<'a> ParseContext<'_> {
         let mut is_enum = true;
         for v in &skip_variants {
             match v.style {
-                ast::Style::Unit => continue,
+                ast::Style::Unit
+                    if !v.attrs.other() && !self.is_untagged(v) && self.ts_type(v).is_none() =>
+                {
+                    continue
+                }
                 _ => {
                     is_enum = false;
                     break;
@@ -67,111 +77,301 @@ impl<'a> ParseContext<'_> {
             }
         }
         if is_enum {
-            let v = &skip_variants
+            // keys are the rust idents, values the serde names
+            let k = skip_variants
                 .iter()
-                .map(|v| v.attrs.name().serialize_name()) // use serde name instead of v.ident
+                .map(|v| unraw(&v.ident))
                 .collect::<Vec<_>>();
-            let k = v.iter().map(|v| ident_from_str(&v)).collect::<Vec<_>>();
-            let verify = if self.gen_guard {
-                let obj = &self.arg_name;
-                let o = (0..v.len()).map(|_| obj.clone());
-                let eq = (0..v.len()).map(|_| eq());
-
-                Some(quote!(
-                    {
+            let v = skip_variants
+                .iter()
+                .map(|v| Lit::Str(self.variant_name(v)))
+                .collect::<Vec<_>>();
+            let docs = skip_variants
+                .iter()
+                .map(|v| self.variant_doc(&v.ident))
+                .collect::<Vec<_>>();
+            return self.derive_unit_enum(&k, &v, &docs);
+        }
 
-                        if (!((#(#o #eq #v)||*))) return false;
-                        return true;
-                    }
-                ))
+        // guards try the tagged variants first, then any #[serde(other)]
+        // catch-all and finally the #[serde(untagged)] variants (as serde does).
+        skip_variants.sort_by_key(|v| {
+            if self.is_untagged(v) {
+                2
+            } else if v.attrs.other() {
+                1
             } else {
-                None
-            };
-            return QuoteMaker {
-                body: quote! ( { #(#k = #v),* } ),
-                verify,
-                is_enum: true,
-            };
-        }
+                0
+            }
+        });
+        let untagged = TagInfo {
+            tag: None,
+            content: None,
+            untagged: true,
+        };
 
         let content = skip_variants
             .iter()
-            .map(|variant| match variant.style {
-                ast::Style::Struct => {
-                    self.derive_struct_variant(&taginfo, variant, &variant.fields, ast_container)
+            .map(|variant| {
+                let taginfo = if self.is_untagged(variant) {
+                    &untagged
+                } else {
+                    &taginfo
+                };
+                if let Some(ts_type) = self.ts_type(variant) {
+                    return self.derive_ts_type_variant(taginfo, variant, ts_type);
                 }
-                ast::Style::Newtype => {
-                    self.derive_newtype_variant(&taginfo, variant, &variant.fields[0])
+                let mut q = match variant.style {
+                    ast::Style::Struct => {
+                        self.derive_struct_variant(taginfo, variant, &variant.fields, ast_container)
+                    }
+                    ast::Style::Newtype => {
+                        self.derive_newtype_variant(taginfo, variant, &variant.fields[0])
+                    }
+                    ast::Style::Tuple => {
+                        self.derive_tuple_variant(taginfo, variant, &variant.fields)
+                    }
+                    ast::Style::Unit => self.derive_unit_variant(taginfo, variant),
+                };
+                if self.gen_guard && !self.is_guarded(variant) && !variant.attrs.other() {
+                    q.verify = Some(self.verify_tag(taginfo, variant));
                 }
-                ast::Style::Tuple => self.derive_tuple_variant(&taginfo, variant, &variant.fields),
-                ast::Style::Unit => self.derive_unit_variant(&taginfo, variant),
+                q
             })
             .collect::<Vec<_>>();
         // OK generate A | B | C etc
-        let newl = nl();
-        let body = content.iter().map(|q| q.body.clone());
-        let nl = content.iter().map(|_| quote!(#newl));
         let verify = if self.gen_guard {
-            let v = content.iter().map(|q| q.verify.clone().unwrap());
-
             let obj = &self.arg_name;
-            let nl = content.iter().map(|_| quote!(#newl));
             // obj can't be null or undefined
-            Some(quote!(
-                {
-                    if (#obj == undefined) return false;
+            let mut v = vec![Stmt::fail_if(obj.clone().loose_eq(Expr::undefined()))];
+            v.extend(content.iter().map(|q| {
+                let variant = Expr::iife(q.verify.clone().unwrap());
+                Stmt::If(variant, Box::new(Stmt::ret(true)))
+            }));
+            v.push(Stmt::ret(false));
+            Some(v)
+        } else {
+            None
+        };
+        let body = content
+            .into_iter()
+            .zip(&skip_variants)
+            .map(|(q, v)| q.body.with_doc(self.variant_doc(&v.ident)))
+            .collect();
+        QuoteMaker {
+            body: Ty::Union(body),
+            verify,
+            members: None,
+        }
+    }
+    /// C-like enum serialized as its discriminants e.g. with serde_repr
+    fn derive_number_enum(&self, variants: &[ast::Variant<'a>]) -> QuoteMaker {
+        let mut k = vec![];
+        let mut v = vec![];
+        let mut docs = vec![];
+        for (key, value) in &self.discriminants {
+            let variant = variants.iter().find(|v| unraw(&v.ident) == *key);
+            if variant.is_some_and(|v| self.is_skipped(v)) {
+                continue;
+            }
+            k.push(key.clone());
+            v.push(value.clone());
+            docs.push(variant.and_then(|v| self.variant_doc(&v.ident)));
+        }
+        self.derive_unit_enum(&k, &v, &docs)
+    }
 
-                    #( #nl if ( ( () => #v )() ) return true; )*
-                    #newl return false;
-                }
-            ))
+    /// enum of unit variants with keys `k`, (serialized) values `v` and docs
+    fn derive_unit_enum(&self, k: &[String], v: &[Lit], docs: &[Option<String>]) -> QuoteMaker {
+        let verify = if !self.gen_guard {
+            None
+        } else if v.is_empty() {
+            // nothing to match
+            Some(vec![Stmt::ret(false)])
+        } else {
+            let obj = &self.arg_name;
+            let eq = v
+                .iter()
+                .map(|v| obj.clone().eq(Expr::Lit(v.clone())))
+                .collect();
+
+            Some(vec![Stmt::check(Expr::or(eq)), Stmt::ret(true)])
+        };
+        let is_enum = self.global_attrs.enum_style != EnumStyle::Union;
+        if self.global_attrs.enum_values {
+            let values = if is_enum {
+                let ident = Expr::ident(&self.ts_name().to_string());
+                k.iter().map(|k| ident.member(k)).collect()
+            } else {
+                v.iter().cloned().map(Expr::Lit).collect()
+            };
+            self.enum_values.replace(Some(Expr::Array(values)));
+        }
+        let members = if is_enum {
+            let members = k.iter().zip(v).zip(docs).map(|((k, v), doc)| Member {
+                key: k.clone(),
+                value: v.clone(),
+                doc: doc.clone(),
+            });
+            Some(members.collect())
+        } else {
+            None
+        };
+        let body = v
+            .iter()
+            .zip(docs)
+            .map(|(v, doc)| Ty::Lit(v.clone()).with_doc(doc.clone()))
+            .collect();
+        QuoteMaker {
+            body: Ty::Union(body),
+            verify,
+            members,
+        }
+    }
+
+    /// the variant's docs and `#[ts(...)]` attributes
+    fn variant_attrs(&self, ident: &syn::Ident) -> Option<&Attrs> {
+        let ident = ident.to_string();
+        self.variant_attrs
+            .iter()
+            .find(|(v, _)| *v == ident)
+            .map(|(_, attrs)| attrs)
+    }
+
+    fn variant_doc(&self, ident: &syn::Ident) -> Option<String> {
+        self.variant_attrs(ident)?.doc.clone()
+    }
+
+    /// variant has `#[ts(skip)]`
+    fn is_skipped(&self, variant: &Variant) -> bool {
+        self.variant_attrs(&variant.ident).is_some_and(|a| a.skip)
+    }
+
+    /// variant has `#[ts(ts_type = "...")]`
+    fn ts_type(&self, variant: &Variant) -> Option<&LitStr> {
+        self.variant_attrs(&variant.ident)?.ts_type.as_ref()
+    }
+
+    /// variant has `#[ts(guard = false)]`
+    fn is_guarded(&self, variant: &Variant) -> bool {
+        self.variant_attrs(&variant.ident).map_or(true, |a| a.guard)
+    }
+
+    /// variant has its own `#[serde(untagged)]`
+    fn is_untagged(&self, variant: &Variant) -> bool {
+        self.untagged_variants.contains(&variant.ident.to_string())
+    }
+
+    /// a `#[serde(other)]` variant will match any tag
+    fn derive_other_variant(&self, taginfo: &TagInfo) -> QuoteMaker {
+        let (body, obj) = match taginfo.tag {
+            Some(tag) => (
+                Ty::Object(vec![Prop::new(tag, Ty::named("string"))]),
+                self.arg_name.member(tag),
+            ),
+            None => (Ty::named("string"), self.arg_name.clone()),
+        };
+        let verify = if self.gen_guard {
+            Some(vec![Stmt::Return(obj.type_is("string"))])
         } else {
             None
         };
         QuoteMaker {
-            body: quote! ( #( #nl | #body)* ),
+            body,
             verify,
-            is_enum: false,
+            members: None,
         }
     }
+
+    /// `#[ts(ts_type = "...")]` gives the shape of the whole variant
+    fn derive_ts_type_variant(
+        &self,
+        taginfo: &TagInfo,
+        variant: &Variant,
+        lit: &LitStr,
+    ) -> QuoteMaker {
+        use std::str::FromStr;
+        let ts_type = lit.value();
+        let tokens_ok = QuoteT::from_str(&ts_type).is_ok();
+        if !tokens_ok {
+            self.err_msg(
+                lit,
+                &format!(
+                    "{}::{}: can't parse type {}",
+                    self.ident, variant.ident, ts_type
+                ),
+            );
+        }
+        let verify = if !self.gen_guard {
+            None
+        } else if !tokens_ok {
+            // reported above, don't report it again for the guard
+            Some(vec![])
+        } else if self.is_guarded(variant) {
+            let mut verify = self.verify_ts_type(&self.arg_name, &ts_type, false, lit);
+            verify.push(Stmt::ret(true));
+            Some(verify)
+        } else {
+            Some(self.verify_tag(taginfo, variant))
+        };
+        QuoteMaker {
+            body: Ty::Raw(ts_type.trim().to_string()),
+            verify,
+            members: None,
+        }
+    }
+
+    /// With `#[ts(guard = false)]` the guard only checks that `obj` is
+    /// tagged as this variant (an untagged variant matches anything).
+    fn verify_tag(&self, taginfo: &TagInfo, variant: &Variant) -> Vec<Stmt> {
+        let obj = &self.arg_name;
+        let variant_name = Expr::string(&self.variant_name(variant));
+        let is_variant = match taginfo.tag {
+            _ if taginfo.untagged => return vec![Stmt::ret(true)],
+            Some(tag) => obj.member(tag).eq(variant_name),
+            None => match variant.style {
+                ast::Style::Unit => obj.clone().eq(variant_name),
+                _ => obj
+                    .member(&self.variant_name(variant))
+                    .eq(Expr::undefined())
+                    .not(),
+            },
+        };
+        vec![Stmt::Return(is_variant)]
+    }
+
     fn derive_unit_variant(&self, taginfo: &TagInfo, variant: &Variant) -> QuoteMaker {
-        let variant_name = variant.attrs.name().serialize_name(); // use serde name instead of variant.ident
-        let eq = eq();
+        if variant.attrs.other() && !taginfo.untagged {
+            return self.derive_other_variant(taginfo);
+        }
+        let variant_name = self.variant_name(variant);
 
         if taginfo.tag.is_none() {
             let verify = if self.gen_guard {
                 let obj = &self.arg_name;
-                Some(quote!(
-                    {
-                        return #obj #eq #variant_name;
-                    }
-                ))
+                Some(vec![Stmt::Return(
+                    obj.clone().eq(Expr::string(&variant_name)),
+                )])
             } else {
                 None
             };
             return QuoteMaker {
-                body: quote!(#variant_name),
+                body: Ty::string(&variant_name),
                 verify,
-                is_enum: false,
+                members: None,
             };
         }
-        let tag = ident_from_str(taginfo.tag.unwrap());
+        let tag = taginfo.tag.unwrap();
+        let obj_tag = self.arg_name.member(tag);
         let verify = if self.gen_guard {
-            let obj = &self.arg_name;
-            Some(quote!(
-                {
-                    return #obj.#tag #eq #variant_name;
-                }
-            ))
+            Some(vec![Stmt::Return(obj_tag.eq(Expr::string(&variant_name)))])
         } else {
             None
         };
         QuoteMaker {
-            body: quote! (
-                { #tag: #variant_name }
-            ),
+            body: Ty::Object(vec![Prop::new(tag, Ty::string(&variant_name))]),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 
@@ -191,74 +391,84 @@ impl<'a> ParseContext<'_> {
         if taginfo.tag.is_none() {
             if taginfo.untagged {
                 let verify = if self.gen_guard {
-                    let v = self.verify_type(&obj, field);
-
-                    Some(quote!( { #v; return true }))
+                    let mut v = self.verify_type(obj, field);
+                    v.push(Stmt::ret(true));
+                    Some(v)
                 } else {
                     None
                 };
                 return QuoteMaker {
-                    body: quote! ( #ty ),
+                    body: ty,
                     verify,
-                    is_enum: false,
+                    members: None,
                 };
             };
-            let tag = ident_from_str(&variant_name);
+            let obj_tag = obj.member(&variant_name);
 
             let verify = if self.gen_guard {
-                let v = quote!(v);
-                let verify = self.verify_type(&v, field);
-                let eq = eq();
-                // #ty might be a Option None and therefore null
-                // OTOH #verify might be assuming not null and not undefined
-                Some(quote!(
-                    {
-                        const v = #obj.#tag;
-                        if (v #eq undefined) return false;
-                        #verify;
-                        return true;
-                    }
-                ))
+                let v = Expr::ident("v");
+                // ty might be a Option None and therefore null
+                // OTOH verify might be assuming not null and not undefined
+                let mut verify = vec![
+                    Stmt::Const("v".into(), obj_tag),
+                    Stmt::fail_if(v.clone().eq(Expr::undefined())),
+                ];
+                verify.extend(self.verify_type(&v, field));
+                verify.push(Stmt::ret(true));
+                Some(verify)
             } else {
                 None
             };
             return QuoteMaker {
-                body: quote! (
-                    { #tag : #ty }
-
-                ),
+                body: Ty::Object(vec![Prop::new(&variant_name, ty)]),
                 verify,
-                is_enum: false,
+                members: None,
             };
         };
-        let tag = ident_from_str(taginfo.tag.unwrap());
+        let tag = taginfo.tag.unwrap();
+        let tag_check = Stmt::check(obj.member(tag).eq(Expr::string(&variant_name)));
+        let tag_prop = Prop::new(tag, Ty::string(&variant_name));
 
         let content = if let Some(content) = taginfo.content {
-            ident_from_str(&content)
+            content
         } else {
-            ident_from_str(CONTENT) // should not get here...
+            // internally tagged: serde writes the tag next to the
+            // newtype's own fields so we get an intersection.
+            let verify = if self.gen_guard {
+                // serde removes the tag before deserializing the newtype
+                // (which might not allow unknown keys)
+                let rest = Expr::ident("rest");
+                let mut block = vec![Stmt::Omit("rest".into(), tag.to_string(), obj.clone())];
+                block.extend(self.verify_type(&rest, field));
+                let verify = vec![tag_check, Stmt::Block(block), Stmt::ret(true)];
+                Some(verify)
+            } else {
+                None
+            };
+            return QuoteMaker {
+                body: Ty::Intersection(vec![Ty::Object(vec![tag_prop]), ty]),
+                verify,
+                members: None,
+            };
         };
 
         let verify = if self.gen_guard {
-            let eq = eq();
-            let verify = self.verify_type(&quote!(val), field);
-            Some(quote!(
-            {
-                if (!(#obj.#tag #eq #variant_name)) return false;
-                const val = #obj.#content;
-                if (val #eq undefined) return false;
-                #verify;
-                return true;
-            }))
+            let val = Expr::ident("val");
+            let mut verify = vec![
+                tag_check,
+                Stmt::Const("val".into(), obj.member(content)),
+                Stmt::fail_if(val.clone().eq(Expr::undefined())),
+            ];
+            verify.extend(self.verify_type(&val, field));
+            verify.push(Stmt::ret(true));
+            Some(verify)
         } else {
             None
         };
         QuoteMaker {
-            body: quote! (
-                { #tag: #variant_name; #content: #ty }
-            ),
+            body: Ty::Object(vec![tag_prop, Prop::new(content, ty)]),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 
@@ -269,137 +479,130 @@ impl<'a> ParseContext<'_> {
         fields: &[ast::Field<'a>],
         ast_container: &ast::Container,
     ) -> QuoteMaker {
-        use std::collections::HashSet;
         let fields = filter_visible(fields);
         if fields.is_empty() {
             return self.derive_unit_variant(taginfo, variant);
         }
 
-        self.check_flatten(&fields, ast_container);
-
-        let contents = self.derive_fields(&fields).collect::<Vec<_>>();
+        let (flatten, fields) = split_flatten(fields);
+        let mut known = self.field_names(&fields);
+        let (flatten, fields) = (self.filter_ts_skip(flatten), self.filter_ts_skip(fields));
+        let contents = self.derive_fields(&fields);
         let variant_name = self.variant_name(variant);
+        let inner = if contents.is_empty() {
+            None
+        } else {
+            Some(Ty::Object(contents.clone()))
+        };
+        let obj = &self.arg_name;
+        let v = Expr::ident("v");
 
-        let last = nl();
-        let nl = contents.iter().map(|_| quote!(#last));
         if taginfo.tag.is_none() {
             if taginfo.untagged {
                 let verify = if self.gen_guard {
-                    let verify = self.verify_fields(&self.arg_name, &fields);
-
-                    Some(quote!(
-                        {
-                            #( #nl #verify;)*
-                            #last return true;
-                        }
-                    ))
+                    let mut verify = self.verify_flatten(obj, &flatten, &known);
+                    verify.extend(self.verify_fields(obj, &fields));
+                    verify.extend(self.verify_known_keys(obj, &known, &flatten));
+                    verify.push(Stmt::ret(true));
+                    Some(verify)
                 } else {
                     None
                 };
                 return QuoteMaker {
-                    body: quote! (
-                        { #(#contents);* }
-                    ),
+                    body: self.intersect_flatten(&flatten, inner),
                     verify,
-                    is_enum: false,
+                    members: None,
                 };
             };
-            let v = &quote!(v);
-            let tag = ident_from_str(&variant_name);
             let verify = if self.gen_guard {
-                let obj = &self.arg_name;
-                let verify = self.verify_fields(&v, &fields);
-                Some(quote!(
-                    {
-                        const v = #obj.#tag;
-                        if (v == undefined) return false;
-                        #(#nl #verify;)*
-                        #last return true;
-                    }
-                ))
+                let mut verify =
+                    self.verify_known_keys(obj, std::slice::from_ref(&variant_name), &[]);
+                verify.push(Stmt::Const("v".into(), obj.member(&variant_name)));
+                verify.push(Stmt::fail_if(v.clone().loose_eq(Expr::undefined())));
+                verify.extend(self.verify_flatten(&v, &flatten, &known));
+                verify.extend(self.verify_fields(&v, &fields));
+                verify.extend(self.verify_known_keys(&v, &known, &flatten));
+                verify.push(Stmt::ret(true));
+                Some(verify)
             } else {
                 None
             };
+            let inner = self.intersect_flatten(&flatten, inner);
             return QuoteMaker {
-                body: quote! (
-                    { #tag : { #(#contents);* }  }
-                ),
+                body: Ty::Object(vec![Prop::new(&variant_name, inner)]),
                 verify,
-                is_enum: false,
+                members: None,
             };
         }
         let tag_str = taginfo.tag.unwrap();
-        let tag = ident_from_str(tag_str);
-
-        if let Some(content) = taginfo.content {
-            let content = ident_from_str(&content);
+        let tag_check = Stmt::check(obj.member(tag_str).eq(Expr::string(&variant_name)));
+        let tag_prop = Prop::new(tag_str, Ty::string(&variant_name));
 
+        if let Some(content_str) = taginfo.content {
             let verify = if self.gen_guard {
-                let obj = &self.arg_name;
-                let v = quote!(v);
-                let verify = self.verify_fields(&v, &fields);
-                let eq = eq();
-                Some(quote!(
-                {
-                    if (!(#obj.#tag #eq #variant_name)) return false;
-                    const v = #obj.#content;
-                    if (v == undefined) return false;
-                    #(#nl #verify;)*
-                    #last return true;
-                }
-                ))
+                let mut verify = vec![tag_check];
+                verify.extend(self.verify_known_keys(
+                    obj,
+                    &[tag_str.into(), content_str.into()],
+                    &[],
+                ));
+                verify.push(Stmt::Const("v".into(), obj.member(content_str)));
+                verify.push(Stmt::fail_if(v.clone().loose_eq(Expr::undefined())));
+                verify.extend(self.verify_flatten(&v, &flatten, &known));
+                verify.extend(self.verify_fields(&v, &fields));
+                verify.extend(self.verify_known_keys(&v, &known, &flatten));
+                verify.push(Stmt::ret(true));
+                Some(verify)
             } else {
                 None
             };
+            let inner = self.intersect_flatten(&flatten, inner);
             QuoteMaker {
-                body: quote! (
-                    { #tag: #variant_name; #content: { #(#contents);* } }
-
-                ),
+                body: Ty::Object(vec![tag_prop, Prop::new(content_str, inner)]),
                 verify,
-                is_enum: false,
+                members: None,
             }
         } else {
-            if let Some(ref cx) = self.ctxt {
-                let fnames = fields
-                    .iter()
-                    .map(|field| field.attrs.name().serialize_name())
-                    .collect::<HashSet<_>>();
-                if fnames.contains(tag_str) {
-                    cx.error(format!(
+            if known.iter().any(|name| name == tag_str) {
+                self.err_msg(
+                    &variant.ident,
+                    &format!(
                         "clash with field in \"{}::{}\". \
                          Maybe use a #[serde(content=\"...\")] attribute.",
                         ast_container.ident, variant_name
-                    ));
-                }
-            };
+                    ),
+                );
+            }
+            known.push(tag_str.to_string());
             let verify = if self.gen_guard {
-                let obj = &self.arg_name;
-                let verify = self.verify_fields(&obj, &fields);
-                let eq = eq();
-                Some(quote!(
-                {
-                    if (!(#obj.#tag #eq #variant_name)) return false;
-                    #(#nl #verify;)*
-                    #last return true;
-                }
-                ))
+                let mut verify = vec![tag_check];
+                verify.extend(self.verify_flatten(obj, &flatten, &known));
+                verify.extend(self.verify_fields(obj, &fields));
+                verify.extend(self.verify_known_keys(obj, &known, &flatten));
+                verify.push(Stmt::ret(true));
+                Some(verify)
             } else {
                 None
             };
+            let mut props = vec![tag_prop];
+            props.extend(contents);
             QuoteMaker {
-                body: quote! (
-                    { #tag: #variant_name; #(#contents);* }
-                ),
+                body: self.intersect_flatten(&flatten, Some(Ty::Object(props))),
                 verify,
-                is_enum: false,
+                members: None,
             }
         }
     }
 
-    #[inline]
+    /// the `#[ts(rename)]` or serde name
     fn variant_name(&self, variant: &Variant) -> String {
-        variant.attrs.name().serialize_name() // use serde name instead of variant.ident
+        match self.variant_attrs(&variant.ident) {
+            Some(Attrs {
+                rename: Some(ref name),
+                ..
+            }) => name.clone(),
+            _ => variant.attrs.name().serialize_name(), // use serde name instead of variant.ident
+        }
     }
 
     fn derive_tuple_variant(
@@ -410,87 +613,61 @@ impl<'a> ParseContext<'_> {
     ) -> QuoteMaker {
         let variant_name = self.variant_name(variant);
         let fields = filter_visible(fields);
-        let contents = self.derive_field_tuple(&fields);
+        let contents = Ty::Tuple(self.derive_field_tuple(&fields).collect());
+        let obj = &self.arg_name;
+        let v = Expr::ident("v");
 
         if taginfo.tag.is_none() {
             if taginfo.untagged {
                 let verify = if self.gen_guard {
-                    let obj = &self.arg_name;
-                    let verify = self.verify_field_tuple(&obj, &fields);
-                    let eq = eq();
-                    let len = Literal::usize_unsuffixed(fields.len());
-
-                    Some(quote!({
-                        if (!Array.isArray(#obj) || !(#obj.length #eq #len)) return false;
-                        #(#verify;)*
-                        return true;
-                    }))
+                    let mut verify = self.verify_field_tuple(obj, &fields);
+                    verify.push(Stmt::ret(true));
+                    Some(verify)
                 } else {
                     None
                 };
                 return QuoteMaker {
-                    body: quote! (
-                     [ #(#contents),* ]
-                    ),
+                    body: contents,
                     verify,
-                    is_enum: false,
+                    members: None,
                 };
             }
-            let tag = ident_from_str(&variant_name);
             let verify = if self.gen_guard {
-                let obj = &self.arg_name;
-                let v = quote!(v);
-                let verify = self.verify_field_tuple(&v, &fields);
-                let len = Literal::usize_unsuffixed(fields.len());
-                let eq = eq();
-                Some(quote!({
-                    const v = #obj.#tag;
-                    if (!Array.isArray(v) || !(v.length #eq #len)) return false;
-                    #(#verify;)*
-                    return true;
-                }))
+                let mut verify = vec![Stmt::Const("v".into(), obj.member(&variant_name))];
+                verify.extend(self.verify_field_tuple(&v, &fields));
+                verify.push(Stmt::ret(true));
+                Some(verify)
             } else {
                 None
             };
             return QuoteMaker {
-                body: quote! (
-                 { #tag : [ #(#contents),* ] }
-
-                ),
+                body: Ty::Object(vec![Prop::new(&variant_name, contents)]),
                 verify,
-                is_enum: false,
+                members: None,
             };
         };
 
-        let tag = ident_from_str(taginfo.tag.unwrap());
-        let content = if let Some(content) = taginfo.content {
-            ident_from_str(&content)
-        } else {
-            ident_from_str(CONTENT)
-        };
+        let tag = taginfo.tag.unwrap();
+        let content = taginfo.content.unwrap_or(CONTENT);
 
         let verify = if self.gen_guard {
-            let eq = eq();
-            let obj = &self.arg_name;
-            let v = quote!(v);
-            let verify = self.verify_field_tuple(&v, &fields);
-            let len = Literal::usize_unsuffixed(fields.len());
-            Some(quote!({
-                if (!(#obj.#tag #eq #variant_name)) return false;
-                const v = #obj.#content;
-                if (!Array.isArray(v) || !(v.length #eq #len)) return false;
-                #(#verify;)*
-                return true;
-            }))
+            let mut verify = vec![
+                Stmt::check(obj.member(tag).eq(Expr::string(&variant_name))),
+                Stmt::Const("v".into(), obj.member(content)),
+            ];
+            verify.extend(self.verify_field_tuple(&v, &fields));
+            verify.push(Stmt::ret(true));
+            Some(verify)
         } else {
             None
         };
         QuoteMaker {
-            body: quote! (
-            { #tag: #variant_name; #content : [ #(#contents),* ] }
-            ),
+            body: Ty::Object(vec![
+                Prop::new(tag, Ty::string(&variant_name)),
+                Prop::new(content, contents),
+            ]),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 }
@@ -6,13 +6,12 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use proc_macro2::Literal;
-use quote::quote;
 use serde_derive_internals::ast;
 
-use super::{filter_visible, patch::eq, patch::nl, ParseContext, QuoteMaker};
+use super::tsast::{Expr, Prop, Stmt, Ty};
+use super::{filter_visible, split_flatten, ParseContext, QuoteMaker};
 
-impl<'a> ParseContext<'_> {
+impl<'a> ParseContext {
     pub(crate) fn derive_struct(
         &self,
         style: ast::Style,
@@ -21,25 +20,21 @@ impl<'a> ParseContext<'_> {
     ) -> QuoteMaker {
         match style {
             ast::Style::Struct => self.derive_struct_named_fields(fields, container),
-            ast::Style::Newtype => self.derive_struct_newtype(&fields[0], container),
+            ast::Style::Newtype => self.derive_struct_newtype(&fields[0]),
             ast::Style::Tuple => self.derive_struct_tuple(fields, container),
             ast::Style::Unit => self.derive_struct_unit(),
         }
     }
 
-    fn derive_struct_newtype(
-        &self,
-        field: &ast::Field<'a>,
-        ast_container: &ast::Container,
-    ) -> QuoteMaker {
+    fn derive_struct_newtype(&self, field: &ast::Field<'a>) -> QuoteMaker {
         if field.attrs.skip_serializing() {
             return self.derive_struct_unit();
         }
-        self.check_flatten(&[field], ast_container);
 
         let verify = if self.gen_guard {
-            let v = self.verify_type(&self.arg_name, field);
-            Some(quote!( { #v; return true } ))
+            let mut v = self.verify_type(&self.arg_name, field);
+            v.push(Stmt::ret(true));
+            Some(v)
         } else {
             None
         };
@@ -47,21 +42,24 @@ impl<'a> ParseContext<'_> {
         QuoteMaker {
             body: self.field_to_ts(field),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 
     fn derive_struct_unit(&self) -> QuoteMaker {
         let verify = if self.gen_guard {
             let obj = &self.arg_name;
-            Some(quote!({ if (#obj == undefined) return false; return true }))
+            Some(vec![
+                Stmt::fail_if(obj.clone().loose_eq(Expr::undefined())),
+                Stmt::ret(true),
+            ])
         } else {
             None
         };
         QuoteMaker {
-            body: quote!({}),
+            body: Ty::Object(vec![]),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 
@@ -71,31 +69,61 @@ impl<'a> ParseContext<'_> {
         ast_container: &ast::Container,
     ) -> QuoteMaker {
         let fields = filter_visible(fields);
-        if fields.is_empty() {
-            return self.derive_struct_unit();
-        };
+        if self.struct_tag.is_none() {
+            if fields.is_empty() {
+                return self.derive_struct_unit();
+            };
 
-        if fields.len() == 1 && ast_container.attrs.transparent() {
-            return self.derive_struct_newtype(&fields[0], ast_container);
+            if fields.len() == 1 && ast_container.attrs.transparent() {
+                return self.derive_struct_newtype(fields[0]);
+            };
+        }
+        let (flatten, fields) = split_flatten(fields);
+        let mut known = self.field_names(&fields);
+        let (flatten, fields) = (self.filter_ts_skip(flatten), self.filter_ts_skip(fields));
+        let mut content = self.derive_fields(&fields);
+        let tag_check = if let Some(ref tag_str) = self.struct_tag {
+            // serde writes a constant `tag: "StructName"` entry
+            if known.iter().any(|name| name == tag_str) {
+                self.err_msg(
+                    &ast_container.ident,
+                    &format!(
+                        "clash with field in \"{}\" for tag \"{}\"",
+                        ast_container.ident, tag_str
+                    ),
+                );
+            }
+            let obj_tag = self.arg_name.member(tag_str);
+            let name = ast_container.attrs.name().serialize_name();
+            content.insert(0, Prop::new(tag_str, Ty::string(&name)));
+            known.push(tag_str.to_string());
+            Some(Stmt::check(obj_tag.eq(Expr::string(&name))))
+        } else {
+            None
+        };
+        let body = if content.is_empty() {
+            None
+        } else {
+            Some(Ty::Object(content))
         };
-        self.check_flatten(&fields, ast_container);
-        let content = self.derive_fields(&fields);
 
         let verify = if self.gen_guard {
             let obj = &self.arg_name;
-            let v = self.verify_fields(&obj, &fields);
-            let n = fields.len();
-            let l = nl();
-            let nl = (0..n).map(|_| quote!(#l));
-            Some(quote!( { if (#obj == undefined) return false; #( #nl #v;)* #l return true } ))
+            let mut v = vec![Stmt::fail_if(obj.clone().loose_eq(Expr::undefined()))];
+            v.extend(tag_check);
+            v.extend(self.verify_flatten(obj, &flatten, &known));
+            v.extend(self.verify_fields(obj, &fields));
+            v.extend(self.verify_known_keys(obj, &known, &flatten));
+            v.push(Stmt::ret(true));
+            Some(v)
         } else {
             None
         };
 
         QuoteMaker {
-            body: quote!({ #(#content);* }),
+            body: self.intersect_flatten(&flatten, body),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 
@@ -110,30 +138,22 @@ impl<'a> ParseContext<'_> {
         }
 
         if fields.len() == 1 && ast_container.attrs.transparent() {
-            return self.derive_struct_newtype(&fields[0], ast_container);
+            return self.derive_struct_newtype(fields[0]);
         };
-        self.check_flatten(&fields, ast_container);
         let content = self.derive_field_tuple(&fields);
         let verify = if self.gen_guard {
-            let obj = &self.arg_name;
-            let verify = self.verify_field_tuple(&obj, &fields);
-            let eq = eq();
-            let len = Literal::usize_unsuffixed(fields.len());
-
             // obj can't be null or undefined
-            Some(quote!({
-            if (!Array.isArray(#obj) || ! #obj.length #eq #len ) return false;
-             #(#verify;)*
-             return true
-             }))
+            let mut v = self.verify_field_tuple(&self.arg_name, &fields);
+            v.push(Stmt::ret(true));
+            Some(v)
         } else {
             None
         };
 
         QuoteMaker {
-            body: quote!([#(#content),*]),
+            body: Ty::Tuple(content.collect()),
             verify,
-            is_enum: false,
+            members: None,
         }
     }
 }
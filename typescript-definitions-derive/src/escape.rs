@@ -0,0 +1,140 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Escape
+//!
+//! rust names (types, generic parameters, function arguments) are spliced
+//! into typescript and javascript. Some of them are perfectly good rust but
+//! reserved in typescript (a generic `number` or a struct `r#delete`) and the
+//! guards we generate share a single namespace so `isT` for a generic `T`
+//! would clash between types. Every such name goes through here.
+
+use super::ident_from_str;
+use proc_macro2::Ident;
+
+// javascript reserved words (including strict mode and future reserved)
+// plus the typescript predefined type names.
+const RESERVED: [&str; 60] = [
+    "any",
+    "arguments",
+    "as",
+    "await",
+    "bigint",
+    "boolean",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "declare",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "never",
+    "new",
+    "null",
+    "number",
+    "object",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "string",
+    "super",
+    "switch",
+    "symbol",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "unknown",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// global javascript functions that a guard `is<Name>` would shadow
+const GLOBALS: [&str; 2] = ["isNaN", "isFinite"];
+
+const GUARD_PREFIX: &str = "is";
+// separates the container and generic parameter names in the guard
+// for a generic parameter. A rust type name would not normally have it.
+const GENERIC_SEP: &str = "__";
+
+pub fn is_reserved(name: &str) -> bool {
+    RESERVED.contains(&name)
+}
+
+/// `r#type` is just `type` to typescript
+pub fn unraw(ident: &Ident) -> String {
+    let s = ident.to_string();
+    match s.get(..2) {
+        Some("r#") => s[2..].to_string(),
+        _ => s,
+    }
+}
+
+/// A rust name that is safe to use as a typescript type, parameter or
+/// variable name: reserved words get a trailing `_` e.g. `delete_`.
+pub fn ts_ident(ident: &Ident) -> Ident {
+    let s = unraw(ident);
+    if is_reserved(&s) {
+        ident_from_str(&(s + "_"))
+    } else {
+        ident_from_str(&s)
+    }
+}
+
+fn escape_guard(s: String) -> Ident {
+    if GLOBALS.contains(&s.as_ref()) {
+        ident_from_str(&(s + "_"))
+    } else {
+        ident_from_str(&s)
+    }
+}
+
+/// name of the type guard for type `ident` i.e. `isIdent`
+pub fn guard_name(ident: &Ident) -> Ident {
+    escape_guard(format!("{}{}", GUARD_PREFIX, ts_ident(ident)))
+}
+
+/// name of the type guard generated for the generic parameter `param` of
+/// type `container` i.e. `isContainer__T`.
+pub fn generic_guard_name(container: &Ident, param: &Ident) -> Ident {
+    escape_guard(format!(
+        "{}{}{}{}",
+        GUARD_PREFIX,
+        ts_ident(container),
+        GENERIC_SEP,
+        ts_ident(param)
+    ))
+}
@@ -6,21 +6,19 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use super::tsast::{Decl, Expr, Guard, Stmt, Ty};
 use super::{
-    ast, guard_name, ident_from_str, is_bytes, last_path_element, patch::eq, Attrs, FieldContext,
+    ast, generic_guard_name, guard_name, is_bytes, last_path_element, ts_ident, FieldContext,
     ParseContext, QuoteT, TSType,
 };
-use proc_macro2::Literal;
-use proc_macro2::TokenStream;
-use quote::quote;
+use quote::ToTokens;
 
 impl<'a> FieldContext<'a> {
     #[allow(unused)]
-    fn verify_type(&self, obj: &'a TokenStream, ty: &syn::Type) -> QuoteT {
+    fn verify_type(&self, obj: &Expr, ty: &syn::Type) -> Vec<Stmt> {
         // obj is an Ident
         // remeber obj is definitely *not* undefined... but because
         // of the option type it *could* be null....
-        let eq = eq();
 
         use syn::Type::*;
         use syn::{
@@ -35,169 +33,177 @@ impl<'a> FieldContext<'a> {
             Reference(TypeReference { elem, .. }) => self.verify_type(obj, elem),
             // fn(a: A,b: B, c:C) -> D
             BareFn(TypeBareFn { output, inputs, .. }) => {
-                return quote!(); // can you type check functions?
+                vec![] // can you type check functions?
             }
-            Never(..) => quote! { false },
+            Never(..) => vec![],
             Tuple(TypeTuple { elems, .. }) => {
-                let elems = elems.iter().enumerate().map(|(i, t)| {
-                    let i = Literal::usize_unsuffixed(i);
-                    let v = quote!(#obj[#i]);
-                    let verify = self.verify_type(&quote!(val), t);
-                    quote! {
-                        {
-                            const val = #v;
-                            if (val #eq undefined) return false;
-                            #verify;
-                        }
-                    }
-                });
+                let val = Expr::ident("val");
+                let elems = elems
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| {
+                        let mut block = vec![
+                            Stmt::Const("val".into(), obj.index(Expr::number(i))),
+                            Stmt::fail_if(val.clone().eq(Expr::undefined())),
+                        ];
+                        block.extend(self.verify_type(&val, t));
+                        Stmt::Block(block)
+                    })
+                    .collect::<Vec<_>>();
 
-                let len = Literal::usize_unsuffixed(elems.len());
-                quote!(
-                    if (! Array.isArray(#obj) || ! #obj.length #eq #len ) return false;
-                    {
-                        #(#elems;)*;
-                    }
-                )
+                vec![verify_length(obj, elems.len()), Stmt::Block(elems)]
             }
 
-            Path(TypePath { path, .. }) => match last_path_element(&path) {
+            Path(TypePath { path, .. }) => match last_path_element(path) {
                 Some(ref ts) => self.verify_generic(obj, ts),
-                _ => quote! {},
+                _ => vec![],
             },
             TraitObject(TypeTraitObject { bounds, .. })
-            | ImplTrait(TypeImplTrait { bounds, .. }) => quote!(),
+            | ImplTrait(TypeImplTrait { bounds, .. }) => vec![],
             Paren(TypeParen { elem, .. }) | Group(TypeGroup { elem, .. }) => {
-                let verify = self.verify_type(obj, elem);
-                quote! {  ( #verify; )  }
+                self.verify_type(obj, elem)
             }
-            Infer(..) | Macro(..) | Verbatim(..) => quote! {},
+            Infer(..) | Macro(..) | Verbatim(..) => vec![],
         }
     }
-    fn verify_array(&self, obj: &'a TokenStream, elem: &syn::Type) -> QuoteT {
+    fn verify_array(&self, obj: &Expr, elem: &syn::Type) -> Vec<Stmt> {
         if let Some(ty) = self.get_path(elem) {
-            if ty.ident == "u8" && is_bytes(&self.field) {
-                let eq = eq();
-                return quote!(if (! (typeof #obj #eq "string")) return false);
+            if ty.ident == "u8" && is_bytes(self.field) {
+                return vec![Stmt::check(obj.type_is("string"))];
             };
         };
-        let verify = self.verify_type(&quote!(x), elem);
-        let brk = if self.attrs.only_first {
-            quote!(break;)
-        } else {
-            quote!()
-        };
-
-        quote! {
-            if (!Array.isArray(#obj)) return false;
-            for (let x of #obj) {
-                #verify;
-                #brk
-            }
+        let mut body = self.verify_type(&Expr::ident("x"), elem);
+        if self.attrs.only_first {
+            body.push(Stmt::Break);
         }
+
+        vec![
+            Stmt::check(obj.is_array()),
+            Stmt::ForOf("x".into(), obj.clone(), body),
+        ]
     }
-    fn verify_generic(&self, obj: &'a TokenStream, ts: &TSType) -> QuoteT {
-        let eq = eq();
-        let check = |o: &TokenStream, tp: &str| -> QuoteT {
-            quote! { if (! (typeof #o #eq #tp) ) return false; }
-        };
+    fn verify_generic(&self, obj: &Expr, ts: &TSType) -> Vec<Stmt> {
+        let check = |o: &Expr, tp: &str| vec![Stmt::check(o.type_is(tp))];
         let name = ts.ident.to_string();
         match name.as_ref() {
             "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
             | "i128" | "isize" | "f64" | "f32" => check(obj, "number"),
             "String" | "str" | "char" | "Path" | "PathBuf" => check(obj, "string"),
-            "bool" => quote! { if (! (typeof #obj #eq "boolean")) return false },
+            "bool" => check(obj, "boolean"),
             "Box" | "Cow" | "Rc" | "Arc" | "Cell" | "RefCell" if ts.args.len() == 1 => {
                 self.verify_type(obj, &ts.args[0])
             }
-            "Duration" => quote! ({
-               if (#obj #eq null) return false;
-               if (!(typeof #obj.secs #eq "number")) return false;
-               if (!(typeof #obj.nanos #eq "number")) return false;
-            }),
-            "SystemTime" => quote! ({
-               if (#obj #eq null) return false;
-               if (!(typeof #obj.secs_since_epoch #eq "number")) return false;
-               if (!(typeof #obj.nanos_since_epoch #eq "number")) return false;
-            }),
+            "Duration" => vec![
+                Stmt::fail_if(obj.clone().eq(Expr::null())),
+                Stmt::check(obj.member("secs").type_is("number")),
+                Stmt::check(obj.member("nanos").type_is("number")),
+            ],
+            "SystemTime" => vec![
+                Stmt::fail_if(obj.clone().eq(Expr::null())),
+                Stmt::check(obj.member("secs_since_epoch").type_is("number")),
+                Stmt::check(obj.member("nanos_since_epoch").type_is("number")),
+            ],
             // std::collections
             "Vec" | "VecDeque" | "LinkedList" if ts.args.len() == 1 => {
                 self.verify_array(obj, &ts.args[0])
             }
-            "HashMap" | "BTreeMap" if ts.args.len() == 2 => {
-                // k will always be strings
-                // but tsc seems to check against  {[K in number]: T }
-                let k = self.type_to_ts(&ts.args[0]).to_string();
-                let k = if k == "number" {
-                    quote! {
-                        if (+k #eq NaN) return false;
-                    }
-                } else {
-                    //self.verify_type(&quote!(k), &ts.args[0]);
-                    // always going to be a string
-                    quote!()
-                };
-                let v = self.verify_type(&quote!(v), &ts.args[1]);
-                let brk = if self.attrs.only_first {
-                    quote!(break;)
-                } else {
-                    quote!()
-                };
-                // obj is definitely not undefined... but it might be null...
-                quote!(
-                    if (#obj #eq null || !(typeof #obj #eq "object")) return false;
-                    for (let k in #obj) {
-                        let v = #obj[k];
-                        #k;
-                        #v;
-                        #brk
-                    }
-                )
-            }
+            "HashMap" | "BTreeMap" if ts.args.len() == 2 => self.verify_map(obj, ts, &[]),
             "HashSet" | "BTreeSet" if ts.args.len() == 1 => self.verify_array(obj, &ts.args[0]),
             "Option" if ts.args.len() == 1 => {
                 let verify = self.verify_type(obj, &ts.args[0]);
-                quote!(  if (!(#obj #eq null)) { // sic! === to null.
-                            #verify
-                        }
-                )
+                // sic! === to null.
+                vec![Stmt::If(
+                    obj.clone().eq(Expr::null()).not(),
+                    Box::new(Stmt::Block(verify)),
+                )]
             }
             "Result" | "Either" if ts.args.len() == 2 => {
-                let v = quote!(v);
-                let k = self.verify_type(&v, &ts.args[0]);
-                let v = self.verify_type(&v, &ts.args[1]);
                 let (left, right) = if name == "Result" {
-                    (quote!(Ok), quote!(Err))
+                    ("Ok", "Err")
                 } else {
-                    (quote!(Left), quote!(Right))
+                    ("Left", "Right")
+                };
+                let v = Expr::ident("v");
+                let side = |ty: &syn::Type, key: &str| {
+                    let mut body = vec![Stmt::fail_if(v.clone().loose_eq(Expr::undefined()))];
+                    body.extend(self.verify_type(&v, ty));
+                    body.push(Stmt::ret(true));
+                    Expr::Arrow(vec!["v".into()], body).call(vec![obj.member(key)])
                 };
-                quote! ({
-                        if (#obj #eq null) return false;
-                        if(
-                            ((v => {if(v == undefined) return false; #k; return true; })(#obj.#left)) ||
-                            ((v => {if(v == undefined) return false; #v; return true; })(#obj.#right))
-                          ) return true;
-                        return false;
-                 } )
+                vec![Stmt::Block(vec![
+                    Stmt::fail_if(obj.clone().eq(Expr::null())),
+                    Stmt::If(
+                        Expr::or(vec![side(&ts.args[0], left), side(&ts.args[1], right)]),
+                        Box::new(Stmt::ret(true)),
+                    ),
+                    Stmt::ret(false),
+                ])]
             }
 
-            "Fn" | "FnOnce" | "FnMut" => quote!( {
-                if (!(typeof #obj #eq "function")) return false;
-            }),
+            "Fn" | "FnOnce" | "FnMut" => check(obj, "function"),
             _ => {
                 let owned = ts.path();
                 let path: Vec<&str> = owned.iter().map(|s| s.as_ref()).collect();
                 match path[..] {
-                    ["chrono", "DateTime"] => {
-                        quote!( if (!(typeof #obj #eq "string")) return false; )
-                    }
+                    ["chrono", "DateTime"] => check(obj, "string"),
+                    ["Self"] => self.verify_self(obj),
                     _ => self.do_really_generic(obj, ts),
                 }
             }
         }
     }
 
-    fn do_really_generic(&self, obj: &'a TokenStream, ts: &TSType) -> QuoteT {
+    /// Check the values of a map. Keys in `skip` belong to the
+    /// other fields of a struct that the map has been `#[serde(flatten)]`ed into.
+    fn verify_map(&self, obj: &Expr, ts: &TSType, skip: &[String]) -> Vec<Stmt> {
+        let (kname, vname) = map_vars(obj);
+        let k = Expr::ident(&kname);
+        let v = Expr::ident(&vname);
+        let mut body = vec![];
+        if !skip.is_empty() {
+            let skip = skip.iter().map(|s| k.clone().eq(Expr::string(s))).collect();
+            body.push(Stmt::If(Expr::or(skip), Box::new(Stmt::Continue)));
+        }
+        body.push(Stmt::Let(vname, obj.index(k.clone())));
+        // k will always be strings
+        // but tsc seems to check against  {[K in number]: T }
+        if self.type_to_ts(&ts.args[0]) == Ty::named("number") {
+            let k = Expr::Unary("+", Box::new(k));
+            body.push(Stmt::fail_if(k.eq(Expr::ident("NaN"))));
+        }
+        body.extend(self.verify_type(&v, &ts.args[1]));
+        if self.attrs.only_first {
+            body.push(Stmt::Break);
+        }
+        // obj is definitely not undefined... but it might be null...
+        vec![
+            Stmt::fail_if(Expr::or(vec![
+                obj.clone().eq(Expr::null()),
+                obj.type_is("object").not(),
+            ])),
+            Stmt::ForIn(kname, obj.clone(), body),
+        ]
+    }
+
+    /// `Self` is checked by the guard we are generating (with the same `typename`)
+    fn verify_self(&self, obj: &Expr) -> Vec<Stmt> {
+        let func = Expr::ident(&guard_name(&self.ctxt.ts_name()).to_string());
+        if self.ctxt.ts_generics.is_empty() {
+            return vec![Stmt::check(func.call(vec![obj.clone()]))];
+        }
+        let params = match self.ctxt.self_type() {
+            Ty::Ref(_, params) => params,
+            _ => vec![],
+        };
+        let call = Expr::Call(
+            Box::new(func),
+            params,
+            vec![obj.clone(), Expr::ident("typename")],
+        );
+        vec![Stmt::check(call)]
+    }
+
+    fn do_really_generic(&self, obj: &Expr, ts: &TSType) -> Vec<Stmt> {
         // Here we go.....
         let ident = &ts.ident;
 
@@ -205,137 +211,225 @@ impl<'a> FieldContext<'a> {
             Some((t, _)) => *t == *ident,
             None => false,
         });
-        let func = guard_name(&ident);
-
-        let (func, gen_params): (TokenStream, TokenStream) = if is_generic {
-            (quote!(#func), quote!(<#ident>))
+        let (func, gen_params) = if is_generic {
+            let func = generic_guard_name(&self.ctxt.ts_name(), ident);
+            (func, vec![Ty::named(&ts_ident(ident).to_string())])
         } else {
-            (quote!(#func), quote!())
+            (guard_name(ident), vec![])
         };
+        let func = Expr::ident(&func.to_string());
         if !ts.args.is_empty() {
             if is_generic {
                 // T<K,V> with T generic ...
-                self.ctxt.err_msg(&format!(
-                    "{}: generic args of a generic type is not supported",
-                    ident
-                ));
-                return quote!(return false);
+                self.ctxt.err_msg(
+                    self.field.original,
+                    &format!("{}: generic args of a generic type is not supported", ident),
+                );
+                return vec![Stmt::ret(false)];
             }
             // OK we have a monomorphisation of a generic type possibly
             // user defined and hopefully also generated
             // we generate a S<type...>(obj, typename) function call.
             let args: Vec<_> = self.derive_syn_types(&ts.args).collect();
-            let a = args.clone();
-            let a = quote!(#(#a),*).to_string();
-            let a = a.trim();
-            let a = Literal::string(&a);
-            quote! { if (!#func#gen_params<#(#args),*>(#obj, #a)) return false; }
+            let typename = args
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let call = Expr::Call(
+                Box::new(func),
+                args,
+                vec![obj.clone(), Expr::string(&typename)],
+            );
+            vec![Stmt::check(call)]
         } else if is_generic {
-            let eq = eq();
             // this will return false if typename is anything other
             // than number boolean, string or possibly object
-            let gen_func = quote!(
-                export const #func = #gen_params(#obj: any, typename: string): #obj is #ident => {
-                    return typeof #obj #eq typename
-                }
-            );
-            self.ctxt.add_extra_guard(gen_func);
+            let name = obj.to_string();
+            let typename = Expr::ident("typename");
+            let gen_func = Guard {
+                name: func.to_string(),
+                params: gen_params.iter().map(|t| t.to_string()).collect(),
+                arg: name,
+                typename: true,
+                ty: gen_params[0].clone(),
+                body: vec![Stmt::Return(
+                    Expr::Unary("typeof", Box::new(obj.clone())).eq(typename.clone()),
+                )],
+            };
+            self.ctxt.add_extra_guard(Decl::Guard(gen_func));
 
-            quote!( if (!#func#gen_params(#obj, typename)) return false; )
+            let call = Expr::Call(Box::new(func), gen_params, vec![obj.clone(), typename]);
+            vec![Stmt::check(call)]
         } else {
-            quote!( if (!#func#gen_params(#obj)) return false; )
+            vec![Stmt::check(func.call(vec![obj.clone()]))]
         }
     }
-    pub fn verify_field(&self, obj: &TokenStream) -> QuoteT {
-        let n = self.field.attrs.name().serialize_name(); // use serde name instead of field.member
-        let n = ident_from_str(&n);
-        let val = quote!(val);
-        let eq = eq();
-        let verify = self.verify_single_type(&val);
+    pub fn verify_field(&self, obj: &Expr) -> Vec<Stmt> {
+        let n = obj.member(&self.ctxt.field_name(self.field));
+        let mut block = vec![Stmt::Const("val".into(), n.clone())];
+        block.extend(self.verify_single_type(&Expr::ident("val")));
 
-        quote! {
-           if (#obj.#n #eq undefined) return false;
-           {
-            const val = #obj.#n;
-            #verify;
-           }
+        if self.ctxt.is_optional(self.field) {
+            return vec![Stmt::If(
+                n.eq(Expr::undefined()).not(),
+                Box::new(Stmt::Block(block)),
+            )];
         }
+        vec![Stmt::fail_if(n.eq(Expr::undefined())), Stmt::Block(block)]
     }
 
-    pub fn verify_single_type(&self, obj: &TokenStream) -> QuoteT {
+    /// a flattened field is checked against the containing object
+    pub fn verify_flatten(&self, obj: &Expr, known: &[String]) -> Vec<Stmt> {
+        if self.attrs.ts_guard.is_none() && self.attrs.ts_type.is_none() {
+            let ty = self.attrs.ts_as.as_ref().unwrap_or(self.field.ty);
+            if let Some(ref ts) = self.get_path(ty) {
+                if ts.is_map() {
+                    return self.verify_map(obj, ts, known);
+                }
+            }
+        }
+        self.verify_single_type(obj)
+    }
+
+    pub fn verify_single_type(&self, obj: &Expr) -> Vec<Stmt> {
         if let Some(ref s) = self.attrs.ts_guard {
             return self.ts_guard(obj, s);
         };
         if let Some(ref s) = self.attrs.ts_type {
+            use std::str::FromStr;
+            // a ts_type that isn't even rust tokens has already been reported
+            if QuoteT::from_str(&s.value()).is_err() {
+                return vec![];
+            }
             return self.ts_guard(obj, s);
         };
         if let Some(ref ty) = self.attrs.ts_as {
-            return self.verify_type(obj, ty);
+            self.verify_type(obj, ty)
         } else {
-            self.verify_type(obj, &self.field.ty)
+            self.verify_type(obj, self.field.ty)
+        }
+    }
+    fn ts_guard(&self, obj: &Expr, guard: &syn::LitStr) -> Vec<Stmt> {
+        self.ctxt
+            .verify_ts_type(obj, &guard.value(), self.attrs.only_first, guard)
+    }
+}
+
+/// The key and value names for a loop over the map `obj`. These are `k` and `v`
+/// unless `obj` is itself a value `v` (or `v1` ...) whose loop would then shadow it.
+fn map_vars(obj: &Expr) -> (String, String) {
+    let level = match obj {
+        Expr::Ident(name) if name == "v" => 1,
+        Expr::Ident(name) if name.starts_with('v') => {
+            name[1..].parse::<usize>().map_or(0, |n| n + 1)
         }
+        _ => 0,
+    };
+    if level == 0 {
+        ("k".into(), "v".into())
+    } else {
+        (format!("k{}", level), format!("v{}", level))
     }
-    fn ts_guard(&self, obj: &'a TokenStream, guard: &'a str) -> QuoteT {
+}
+
+/// `if (!Array.isArray(obj) || !(obj.length === len)) return false;`
+pub fn verify_length(obj: &Expr, len: usize) -> Stmt {
+    Stmt::fail_if(Expr::or(vec![
+        obj.is_array().not(),
+        obj.member("length").eq(Expr::number(len)).not(),
+    ]))
+}
+
+impl<'a> ParseContext {
+    /// check `obj` against a user supplied typescript type (errors point at `at`)
+    pub fn verify_ts_type<A: ToTokens>(
+        &self,
+        obj: &Expr,
+        guard: &str,
+        only_first: bool,
+        at: A,
+    ) -> Vec<Stmt> {
         use super::typescript::Typescript;
-        let mut t = Typescript::with_first(self.attrs.only_first);
+        let mut t = Typescript::with_first(only_first);
         match t.parse(obj, guard) {
-            Ok(tokens) => tokens,
+            Ok(stmts) => stmts,
             Err(msg) => {
-                self.ctxt.err_msg(&msg.to_string());
-                quote!()
+                // proc-macro2 0.4 has no `Span::subspan` so we can't point inside the
+                // string literal: the error spans all of it and `msg` gives the column.
+                self.err_msg(at, &format!("can't parse {:?}: {}", guard, msg));
+                vec![]
             }
         }
     }
-}
-
-impl<'a> ParseContext<'a> {
-    pub fn verify_type(&'a self, obj: &'a TokenStream, field: &'a ast::Field<'a>) -> QuoteT {
-        let attrs = Attrs::from_field(field, self.ctxt);
-        let verify = FieldContext {
-            attrs,
-            field,
-            ctxt: &self,
-        };
-        verify.verify_single_type(obj)
+    pub fn verify_type(&'a self, obj: &Expr, field: &'a ast::Field<'a>) -> Vec<Stmt> {
+        self.field_context(field).verify_single_type(obj)
     }
-    pub fn verify_field(&'a self, obj: &'a TokenStream, field: &'a ast::Field<'a>) -> QuoteT {
-        let attrs = Attrs::from_field(field, self.ctxt);
-
-        let verify = FieldContext {
-            attrs,
-            field,
-            ctxt: &self,
-        };
-        verify.verify_field(obj)
+    pub fn verify_field(&'a self, obj: &Expr, field: &'a ast::Field<'a>) -> Vec<Stmt> {
+        self.field_context(field).verify_field(obj)
     }
-    pub fn verify_fields(
-        &'a self,
-        obj: &'a TokenStream,
-        fields: &'a [&'a ast::Field<'a>],
-    ) -> impl Iterator<Item = QuoteT> + 'a {
-        fields.iter().map(move |f| self.verify_field(obj, f))
+    pub fn verify_fields(&'a self, obj: &Expr, fields: &'a [&'a ast::Field<'a>]) -> Vec<Stmt> {
+        fields
+            .iter()
+            .flat_map(|f| self.verify_field(obj, f))
+            .collect()
     }
-    pub fn verify_field_tuple(
+    /// flattened fields share the object with the `known` fields
+    pub fn verify_flatten(
         &'a self,
-        obj: &'a TokenStream,
+        obj: &Expr,
         fields: &'a [&'a ast::Field<'a>],
-    ) -> impl Iterator<Item = QuoteT> + 'a {
-        let eq = eq();
-        fields.iter().enumerate().map(move |(i, f)| {
-            let i = Literal::usize_unsuffixed(i);
-            let n = quote!(#obj[#i]);
-            let verify = self.verify_type(&quote!(val), f);
-            quote! {
-                if (#n #eq undefined) return false;
-                {
-                    const val = #n;
-                    #verify;
-                }
-            }
-        })
+        known: &[String],
+    ) -> Vec<Stmt> {
+        // we can't know the keys of a flattened struct so
+        // we can't tell which of the other keys belong to a map
+        let opaque = fields.iter().any(|f| !self.is_map(f));
+        fields
+            .iter()
+            .filter(|f| !(opaque && self.is_map(f)))
+            .flat_map(|f| self.field_context(f).verify_flatten(obj, known))
+            .collect()
+    }
+    /// `obj` is an array of `fields`
+    pub fn verify_field_tuple(&'a self, obj: &Expr, fields: &'a [&'a ast::Field<'a>]) -> Vec<Stmt> {
+        let val = Expr::ident("val");
+        let mut stmts = vec![verify_length(obj, fields.len())];
+        for (i, f) in fields.iter().enumerate() {
+            let n = obj.index(Expr::number(i));
+            let mut block = vec![Stmt::Const("val".into(), n.clone())];
+            block.extend(self.verify_type(&val, f));
+            stmts.push(Stmt::fail_if(n.eq(Expr::undefined())));
+            stmts.push(Stmt::Block(block));
+        }
+        stmts
+    }
+
+    /// With `#[serde(deny_unknown_fields)]`, `#[ts(strict)]` or `#[ts(deny_unknown)]`
+    /// reject objects with keys other than `known`. We don't know
+    /// the keys of `#[serde(flatten)]`ed fields so then we give up.
+    pub fn verify_known_keys(
+        &self,
+        obj: &Expr,
+        known: &[String],
+        flatten: &[&ast::Field<'a>],
+    ) -> Vec<Stmt> {
+        if !self.deny_unknown || !flatten.is_empty() {
+            return vec![];
+        }
+        let body = if known.is_empty() {
+            Stmt::ret(false)
+        } else {
+            let k = Expr::ident("k");
+            let known = known
+                .iter()
+                .map(|name| k.clone().eq(Expr::string(name)))
+                .collect();
+            Stmt::check(Expr::or(known))
+        };
+        vec![Stmt::ForIn("k".into(), obj.clone(), vec![body])]
     }
 
-    fn add_extra_guard(&'a self, tokens: QuoteT) {
-        self.extra.borrow_mut().push(tokens);
+    fn add_extra_guard(&'a self, guard: Decl) {
+        self.extra.borrow_mut().push(guard);
     }
 }
@@ -13,28 +13,33 @@
 extern crate proc_macro;
 #[macro_use]
 extern crate cfg_if;
-use proc_macro2::Ident;
-use quote::quote;
-use serde_derive_internals::{ast, Ctxt, Derive};
+use proc_macro2::{Ident, Span};
+use quote::{quote, quote_spanned, ToTokens};
+use serde_derive_internals::{ast, Derive};
 // use std::str::FromStr;
 use std::cell::RefCell;
+use syn::spanned::Spanned;
 use syn::DeriveInput;
 
 mod attrs;
+mod ctxt;
 mod derive_enum;
 mod derive_struct;
+mod escape;
 mod guards;
-mod patch;
+mod printer;
 mod tests;
 mod tots;
+mod tsast;
 mod typescript;
 mod utils;
 
-use attrs::Attrs;
+use attrs::{Attrs, EnumStyle, FieldAttrs, Strict};
+use ctxt::{to_compile_errors, Ctxt};
+use escape::*;
+use tsast::{Decl, Expr, Guard, Lit, Member, Prop, Stmt, Ty};
 use utils::*;
 
-use patch::patch;
-
 // too many TokenStreams around! give it a different name
 type QuoteT = proc_macro2::TokenStream;
 
@@ -43,16 +48,15 @@ type QuoteT = proc_macro2::TokenStream;
 type Bounds = Vec<TSType>;
 
 struct QuoteMaker {
-    pub body: QuoteT,
-    pub verify: Option<QuoteT>,
-    pub is_enum: bool,
+    pub body: Ty,
+    pub verify: Option<Vec<Stmt>>,    // body of the type guard
+    pub members: Option<Vec<Member>>, // a typescript enum rather than a type
 }
 #[allow(unused)]
 fn is_wasm32() -> bool {
     use std::env;
-    match env::var("WASM32") {
-        Ok(ref v) => return v == "1",
-        _ => {}
+    if let Ok(ref v) = env::var("WASM32") {
+        return v == "1";
     }
     let mut t = env::args().skip_while(|t| t != "--target").skip(1);
     if let Some(target) = t.next() {
@@ -63,13 +67,12 @@ fn is_wasm32() -> bool {
     false
 }
 
-/// derive proc_macro to expose Typescript definitions to `wasm-bindgen`.
-///
-/// Please see documentation at [crates.io](https://crates.io/crates/typescript-definitions).
-///
 cfg_if! {
     if #[cfg(any(debug_assertions, feature = "export-typescript"))] {
 
+        /// derive proc_macro to expose Typescript definitions to `wasm-bindgen`.
+        ///
+        /// Please see documentation at [crates.io](https://crates.io/crates/typescript-definitions).
         #[proc_macro_derive(TypescriptDefinition, attributes(ts))]
         pub fn derive_typescript_definition(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
@@ -82,6 +85,9 @@ cfg_if! {
         }
     } else {
 
+        /// derive proc_macro to expose Typescript definitions to `wasm-bindgen`.
+        ///
+        /// Please see documentation at [crates.io](https://crates.io/crates/typescript-definitions).
         #[proc_macro_derive(TypescriptDefinition, attributes(ts))]
         pub fn derive_typescript_definition(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             proc_macro::TokenStream::new()
@@ -89,13 +95,12 @@ cfg_if! {
     }
 }
 
-/// derive proc_macro to expose Typescript definitions as a static function.
-///
-/// Please see documentation at [crates.io](https://crates.io/crates/typescript-definitions).
-///
 cfg_if! {
     if #[cfg(any(debug_assertions, feature = "export-typescript"))] {
 
+        /// derive proc_macro to expose Typescript definitions as a static function.
+        ///
+        /// Please see documentation at [crates.io](https://crates.io/crates/typescript-definitions).
         #[proc_macro_derive(TypeScriptify, attributes(ts))]
         pub fn derive_type_script_ify(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             let input = QuoteT::from(input);
@@ -104,6 +109,9 @@ cfg_if! {
         }
     } else {
 
+        /// derive proc_macro to expose Typescript definitions as a static function.
+        ///
+        /// Please see documentation at [crates.io](https://crates.io/crates/typescript-definitions).
         #[proc_macro_derive(TypeScriptify, attributes(ts))]
         pub fn derive_type_script_ify(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             proc_macro::TokenStream::new()
@@ -114,9 +122,12 @@ cfg_if! {
 #[allow(unused)]
 fn do_derive_typescript_definition(input: QuoteT) -> QuoteT {
     let verify = cfg!(feature = "type-guards");
-    let parsed = Typescriptify::parse(verify, input);
+    let parsed = match Typescriptify::parse(verify, input) {
+        Ok(parsed) => parsed,
+        Err(errors) => return to_compile_errors(&errors),
+    };
     let export_string = parsed.wasm_string();
-    let name = parsed.ctxt.ident.to_string().to_uppercase();
+    let name = parsed.ctxt.ts_name().to_string().to_uppercase();
 
     let export_ident = ident_from_str(&format!("TS_EXPORT_{}", name));
 
@@ -147,7 +158,7 @@ fn do_derive_typescript_definition(input: QuoteT) -> QuoteT {
         ));
     }
     if let Some("1") = option_env!("TFY_SHOW_CODE") {
-        eprintln!("{}", patch(&q.to_string()));
+        eprintln!("{}", q);
     }
 
     q
@@ -157,7 +168,10 @@ fn do_derive_typescript_definition(input: QuoteT) -> QuoteT {
 fn do_derive_type_script_ify(input: QuoteT) -> QuoteT {
     let verify = cfg!(feature = "type-guards");
 
-    let parsed = Typescriptify::parse(verify, input);
+    let parsed = match Typescriptify::parse(verify, input) {
+        Ok(parsed) => parsed,
+        Err(errors) => return to_compile_errors(&errors),
+    };
     let export_string = parsed.wasm_string();
     let ident = &parsed.ctxt.ident;
 
@@ -176,7 +190,7 @@ fn do_derive_type_script_ify(input: QuoteT) -> QuoteT {
     } else {
         quote!()
     };
-    let ret = quote! {
+    let mut ret = quote! {
 
         impl #impl_generics ::typescript_definitions::TypeScriptifyTrait for #ident #ty_generics #where_clause {
             fn type_script_ify() ->  ::std::borrow::Cow<'static,str> {
@@ -186,125 +200,116 @@ fn do_derive_type_script_ify(input: QuoteT) -> QuoteT {
         }
 
     };
+    ret.extend(parsed.assert_referenced());
     if let Some("1") = option_env!("TFY_SHOW_CODE") {
-        eprintln!("{}", patch(&ret.to_string()));
+        eprintln!("{}", ret);
     }
 
     ret
 }
 struct Typescriptify {
-    ctxt: ParseContext<'static>,
+    ctxt: ParseContext,
     body: QuoteMaker,
 }
 impl Typescriptify {
+    /// fail to compile if a type we refer to by name isn't `TypeScriptify` too
+    fn assert_referenced(&self) -> QuoteT {
+        let referenced = self.ctxt.referenced.borrow();
+        if referenced.is_empty() {
+            return quote!();
+        }
+        let ident = &self.ctxt.ident;
+        let (impl_generics, ty_generics, where_clause) = self.ctxt.rust_generics.split_for_impl();
+        let asserts = referenced
+            .iter()
+            .map(|ty| quote_spanned!(ty.span()=> _assert::<#ty>();));
+        quote! {
+            const _: () = {
+                fn _assert<T: ?Sized + ::typescript_definitions::TypeScriptifyTrait>() {}
+                // inside an impl `Self` is the container and its fields' bounds (e.g. `T: 'a`) hold
+                impl #impl_generics #ident #ty_generics #where_clause {
+                    #[allow(dead_code)]
+                    fn _assert_referenced() {
+                        #(#asserts)*
+                    }
+                }
+            };
+        }
+    }
     fn wasm_string(&self) -> String {
-        if self.body.is_enum {
-            format!(
-                "{}export enum {} {};",
-                self.ctxt.global_attrs.to_comment_str(),
-                self.ts_ident_str(),
-                self.ts_body_str()
-            )
-        } else {
-            format!(
-                "{}export type {} = {};",
-                self.ctxt.global_attrs.to_comment_str(),
-                self.ts_ident_str(),
-                self.ts_body_str()
-            )
+        let decl = match self.body.members {
+            Some(ref members) => Decl::Enum {
+                name: self.ctxt.ts_name().to_string(),
+                is_const: self.ctxt.global_attrs.enum_style == EnumStyle::ConstEnum,
+                members: members.clone(),
+            },
+            None => Decl::Type {
+                name: self.ctxt.ts_name().to_string(),
+                params: self.ts_generics(false),
+                ty: self.body.body.clone(),
+            },
+        };
+        let decl = match self.ctxt.global_attrs.doc {
+            Some(ref doc) => Decl::Doc(doc.clone(), Box::new(decl)),
+            None => decl,
+        };
+        let format = &self.ctxt.global_attrs.format;
+        let mut s = format.decl(&decl);
+        if let Some(ref values) = *self.ctxt.enum_values.borrow() {
+            let values = Decl::Const {
+                name: format!("{}Values", self.ctxt.ts_name()),
+                value: values.clone(),
+            };
+            s.push('\n');
+            s.push_str(&format.decl(&values));
         }
+        s
     }
     fn wasm_verify(&self) -> Option<String> {
         match self.body.verify {
             None => None,
             Some(ref body) => {
-                let mut s = {
-                    let ident = &self.ctxt.ident;
-                    let obj = &self.ctxt.arg_name;
-                    let body = body.to_string();
-                    let body = patch(&body);
-
-                    let generics = self.ts_generics(false);
-                    let generics_wb = &generics; // self.ts_generics(true);
-                    let is_generic = !self.ctxt.ts_generics.is_empty();
-                    let name = guard_name(&ident);
-                    if is_generic {
-                        format!(
-                            "export const {name} = {generics_wb}({obj}: any, typename: string): \
-                             {obj} is {ident}{generics} => {body}",
-                            name = name,
-                            obj = obj,
-                            body = body,
-                            generics = generics,
-                            generics_wb = generics_wb,
-                            ident = ident
-                        )
-                    } else {
-                        format!(
-                            "export const {name} = {generics_wb}({obj}: any): \
-                             {obj} is {ident}{generics} => {body}",
-                            name = name,
-                            obj = obj,
-                            body = body,
-                            generics = generics,
-                            generics_wb = generics_wb,
-                            ident = ident
-                        )
-                    }
+                let params = self.ts_generics(false);
+                let guard = Guard {
+                    name: guard_name(&self.ctxt.ts_name()).to_string(),
+                    ty: Ty::Ref(
+                        self.ctxt.ts_name().to_string(),
+                        params.iter().map(|p| Ty::named(p)).collect(),
+                    ),
+                    params,
+                    arg: self.ctxt.arg_name.to_string(),
+                    typename: !self.ctxt.ts_generics.is_empty(),
+                    body: body.clone(),
                 };
-                for txt in self.extra_verify() {
+                let format = &self.ctxt.global_attrs.format;
+                let mut s = format.decl(&Decl::Guard(guard));
+                for extra in self.ctxt.extra.borrow().iter() {
                     s.push('\n');
-                    s.push_str(&txt);
+                    s.push_str(&format.decl(extra));
                 }
                 Some(s)
             }
         }
     }
-    fn extra_verify(&self) -> Vec<String> {
-        let v = self.ctxt.extra.borrow();
-        v.iter()
-            .map(|extra| {
-                let e = extra.to_string();
 
-                let extra = patch(&e);
-                "// generic test  \n".to_string() + &extra
-            })
-            .collect()
-    }
-
-    fn ts_ident_str(&self) -> String {
-        let ts_ident = self.ts_ident().to_string();
-        patch(&ts_ident).into()
-    }
-    fn ts_body_str(&self) -> String {
-        let ts = self.body.body.to_string();
-        let ts = patch(&ts);
-        ts.into()
-    }
-    fn ts_generics(&self, with_bound: bool) -> QuoteT {
-        let args_wo_lt: Vec<_> = self.ts_generic_args_wo_lifetimes(with_bound).collect();
-        if args_wo_lt.is_empty() {
-            quote!()
-        } else {
-            quote!(<#(#args_wo_lt),*>)
-        }
-    }
-    /// type name suitable for typescript i.e. *no* 'a lifetimes
-    fn ts_ident(&self) -> QuoteT {
-        let ident = &self.ctxt.ident;
-        let generics = self.ts_generics(false);
-        quote!(#ident#generics)
+    fn ts_generics(&self, with_bound: bool) -> Vec<String> {
+        self.ts_generic_args_wo_lifetimes(with_bound).collect()
     }
 
-    fn ts_generic_args_wo_lifetimes(&self, with_bounds: bool) -> impl Iterator<Item = QuoteT> + '_ {
+    /// type parameters suitable for typescript i.e. *no* 'a lifetimes
+    fn ts_generic_args_wo_lifetimes(&self, with_bounds: bool) -> impl Iterator<Item = String> + '_ {
         self.ctxt.ts_generics.iter().filter_map(move |g| match g {
             Some((ref ident, ref bounds)) => {
+                let ident = ts_ident(ident).to_string();
                 // we ignore trait bounds for typescript
                 if bounds.is_empty() || !with_bounds {
-                    Some(quote! (#ident))
+                    Some(ident)
                 } else {
-                    let bounds = bounds.iter().map(|ts| &ts.ident);
-                    Some(quote! { #ident extends #(#bounds)&* })
+                    let bounds = bounds
+                        .iter()
+                        .map(|ts| ts_ident(&ts.ident).to_string())
+                        .collect::<Vec<_>>();
+                    Some(format!("{} extends {}", ident, bounds.join(" & ")))
                 }
             }
 
@@ -312,55 +317,129 @@ impl Typescriptify {
         })
     }
 
-    fn parse(gen_verifier: bool, input: QuoteT) -> Self {
-        let input: DeriveInput = syn::parse2(input).unwrap();
+    /// All the errors found are returned, each with the span of the
+    /// tokens at fault.
+    fn parse(gen_verifier: bool, input: QuoteT) -> Result<Self, Vec<syn::Error>> {
+        let mut input: DeriveInput = syn::parse2(input).map_err(|e| vec![e])?;
 
         let cx = Ctxt::new();
+        let struct_tag = attrs::take_struct_tag(&mut input);
+        let try_from = attrs::take_try_from(&mut input, &cx);
+        let untagged_variants = attrs::take_untagged_variants(&mut input);
+        let variant_attrs = attrs::variant_attrs(&input, &cx);
         let mut attrs = attrs::Attrs::new();
         attrs.push_doc_comment(&input.attrs);
-        attrs.push_attrs(&input.ident, &input.attrs, Some(&cx));
+        if let Ok(format) = std::env::var("TFY_FORMAT") {
+            if let Err(msg) = attrs.format.update(&format) {
+                cx.error_at(Span::call_site(), format!("TFY_FORMAT: {}", msg));
+            }
+        }
+        if let Ok(strict) = std::env::var("TFY_STRICT") {
+            match Strict::from_str(&strict) {
+                Some(strict) => attrs.strict = strict,
+                None => cx.error_at(
+                    Span::call_site(),
+                    format!(
+                        r#"TFY_STRICT: must be true, false or "unknown" not "{}""#,
+                        strict
+                    ),
+                ),
+            }
+        }
+        attrs.push_attrs(&input.ident, &input.attrs, &cx);
+        if attrs.number_repr.is_none() && has_discriminants(&input.data) {
+            if let Some(repr) = attrs::int_repr(&input.attrs) {
+                cx.error_spanned_by(
+                    repr,
+                    format!(
+                        r#"{}: add #[ts(repr = "number")] if this enum is serialized as its discriminants (e.g. with serde_repr) or #[ts(repr = "string")] if not"#,
+                        input.ident
+                    ),
+                );
+            }
+        }
+        let discriminants = if attrs.number_repr == Some(true) {
+            discriminants(&input.data, &cx)
+        } else {
+            vec![]
+        };
 
-        let container = ast::Container::from_ast(&cx, &input, Derive::Serialize);
+        // serde's own errors have no spans so we point at the container
+        let serde_cx = serde_derive_internals::Ctxt::new();
+        let original = ast::Container::from_ast(&serde_cx, &input, Derive::Serialize);
+        // the wire format is given by the proxy type (if any)
+        let proxy = if attrs.deserialize {
+            original.attrs.type_from().or(try_from.as_ref())
+        } else {
+            original.attrs.type_into()
+        };
+        let proxy_input = proxy.map(|ty| proxy_input(&input, ty));
+        let proxy_container = proxy_input
+            .as_ref()
+            .map(|input| ast::Container::from_ast(&serde_cx, input, Derive::Serialize));
+        if let Err(msg) = serde_cx.check() {
+            for msg in split_errors(&msg) {
+                cx.error_spanned_by(&input.ident, msg);
+            }
+        }
+        let container = proxy_container.as_ref().unwrap_or(&original);
         let ts_generics = ts_generics(container.generics);
         let gv = gen_verifier && attrs.guard;
+        // only an explicit #[ts(strict)] (not TFY_STRICT) or #[ts(deny_unknown)]
+        // changes what the guard accepts
+        let deny_unknown = container.attrs.deny_unknown_fields()
+            || attrs.deny_unknown.unwrap_or(attrs.strict_keys);
+        let field_attrs = attrs::field_attrs(&container.data, &cx);
+
+        let ctxt = ParseContext {
+            ctxt: cx,
+            arg_name: Expr::ident("obj"),
+            global_attrs: attrs,
+            gen_guard: gv,
+            ident: container.ident.clone(),
+            ts_generics,
+            rust_generics: container.generics.clone(),
+            extra: RefCell::new(vec![]),
+            default: !container.attrs.default().is_none(),
+            deny_unknown,
+            struct_tag,
+            untagged_variants,
+            variant_attrs,
+            field_attrs,
+            discriminants,
+            enum_values: RefCell::new(None),
+            referenced: RefCell::new(vec![]),
+        };
 
-        let (typescript, ctxt) = {
-            let pctxt = ParseContext {
-                ctxt: Some(&cx),
-                arg_name: quote!(obj),
-                global_attrs: attrs,
-                gen_guard: gv,
-                ident: container.ident.clone(),
-                ts_generics,
-                rust_generics: container.generics.clone(),
-                extra: RefCell::new(vec![]),
-            };
-
-            let typescript = match container.data {
-                ast::Data::Enum(ref variants) => pctxt.derive_enum(variants, &container),
-                ast::Data::Struct(style, ref fields) => {
-                    pctxt.derive_struct(style, fields, &container)
-                }
-            };
-            // erase serde context
-            (
-                typescript,
-                ParseContext {
-                    ctxt: None,
-                    ..pctxt
-                },
-            )
+        let typescript = match container.data {
+            ast::Data::Enum(ref variants) => ctxt.derive_enum(variants, container),
+            ast::Data::Struct(style, ref fields) => ctxt.derive_struct(style, fields, container),
         };
 
-        // consumes context panics with errors
-        if let Err(m) = cx.check() {
-            panic!(m);
-        }
-        Self {
+        ctxt.ctxt.check()?;
+        Ok(Self {
             ctxt,
             body: typescript,
-        }
+        })
+    }
+}
+
+/// serde joins its errors into one "2 errors:\n\t# ...\n\t# ..." message
+fn split_errors(msg: &str) -> Vec<&str> {
+    let mut errors = msg.split("\n\t# ").collect::<Vec<_>>();
+    if errors.len() > 1 {
+        errors.remove(0);
     }
+    errors
+}
+
+/// A newtype `struct Ident<..>(Proxy);` standing in for a container
+/// that serde (de)serializes via `#[serde(into/from/try_from = "Proxy")]`.
+fn proxy_input(input: &DeriveInput, proxy: &syn::Type) -> DeriveInput {
+    let ident = &input.ident;
+    let generics = &input.generics;
+    let where_clause = &generics.where_clause;
+    syn::parse2(quote!(struct #ident #generics (#proxy) #where_clause;)).unwrap()
 }
 
 fn ts_generics(g: &syn::Generics) -> Vec<Option<(Ident, Bounds)>> {
@@ -382,8 +461,7 @@ fn ts_generics(g: &syn::Generics) -> Vec<Option<(Ident, Bounds)>> {
                         TypeParamBound::Trait(t) => Some(&t.path),
                         _ => None, // skip lifetimes for bounds
                     })
-                    .map(last_path_element)
-                    .filter_map(|b| b)
+                    .filter_map(last_path_element)
                     .collect::<Vec<_>>();
 
                 Some((ty.ident.clone(), bounds))
@@ -419,6 +497,9 @@ impl TSType {
     fn path(&self) -> Vec<String> {
         self.path.iter().map(|i| i.to_string()).collect() // hold the memory
     }
+    fn is_map(&self) -> bool {
+        (self.ident == "HashMap" || self.ident == "BTreeMap") && self.args.len() == 2
+    }
 }
 fn last_path_element(path: &syn::Path) -> Option<TSType> {
     let fullpath = path
@@ -476,9 +557,9 @@ fn last_path_element(path: &syn::Path) -> Option<TSType> {
 }
 
 pub(crate) struct FieldContext<'a> {
-    pub ctxt: &'a ParseContext<'a>, // global parse context
-    pub field: &'a ast::Field<'a>,  // field being parsed
-    pub attrs: Attrs,               // field attributes
+    pub ctxt: &'a ParseContext,    // global parse context
+    pub field: &'a ast::Field<'a>, // field being parsed
+    pub attrs: &'a Attrs,          // field attributes
 }
 
 impl<'a> FieldContext<'a> {
@@ -486,92 +567,167 @@ impl<'a> FieldContext<'a> {
         use syn::Type::Path;
         use syn::TypePath;
         match ty {
-            Path(TypePath { path, .. }) => last_path_element(&path),
+            Path(TypePath { path, .. }) => last_path_element(path),
             _ => None,
         }
     }
 }
 
-pub(crate) struct ParseContext<'a> {
-    ctxt: Option<&'a Ctxt>, // serde parse context for error reporting
-    arg_name: QuoteT,       // top level "name" of argument for verifier
-    global_attrs: Attrs,    // global #[ts(...)] attributes
-    gen_guard: bool,        // generate type guard for this struct/enum
-    ident: syn::Ident,      // name of enum struct
+pub(crate) struct ParseContext {
+    ctxt: Ctxt,                                // errors found while parsing
+    arg_name: Expr,                            // top level "name" of argument for verifier
+    global_attrs: Attrs,                       // global #[ts(...)] attributes
+    gen_guard: bool,                           // generate type guard for this struct/enum
+    ident: syn::Ident,                         // name of enum struct
     ts_generics: Vec<Option<(Ident, Bounds)>>, // None means a lifetime parameter
-    rust_generics: syn::Generics, // original rust generics
-    extra: RefCell<Vec<QuoteT>>, // for generic verifier hack!
+    rust_generics: syn::Generics,              // original rust generics
+    extra: RefCell<Vec<Decl>>,                 // for generic verifier hack!
+    default: bool,                             // container has #[serde(default)]
+    deny_unknown: bool,                        // guards reject undeclared keys
+    struct_tag: Option<String>,                // struct has #[serde(tag = "...")]
+    untagged_variants: Vec<String>,            // enum variants with #[serde(untagged)]
+    variant_attrs: Vec<(String, Attrs)>,       // enum variant docs and #[ts(...)]
+    field_attrs: FieldAttrs,                   // #[ts(...)] for each field
+    discriminants: Vec<(String, Lit)>,         // C-like enum values when serialized as numbers
+    enum_values: RefCell<Option<Expr>>,        // array of unit enum values for #[ts(enum_values)]
+    referenced: RefCell<Vec<syn::Type>>,       // user types that must also be TypeScriptify
 }
 
-impl<'a> ParseContext<'a> {
+impl<'a> ParseContext {
     // Some helpers
 
-    fn err_msg(&self, msg: &str) {
-        if let Some(ctxt) = self.ctxt {
-            ctxt.error(msg);
-        } else {
-            panic!(msg.to_string())
+    fn err_msg<A: ToTokens>(&self, tokens: A, msg: &str) {
+        self.ctxt.error_spanned_by(tokens, msg);
+    }
+
+    /// the container's `#[ts(rename)]` or rust name
+    fn ts_name(&self) -> Ident {
+        match self.global_attrs.rename {
+            Some(ref name) => ts_ident(&ident_from_str(name)),
+            None => ts_ident(&self.ident),
         }
     }
 
-    fn field_to_ts(&self, field: &ast::Field<'a>) -> QuoteT {
-        let attrs = Attrs::from_field(field, self.ctxt);
+    /// what `Self` is in typescript e.g. `Tree<T>`
+    fn self_type(&self) -> Ty {
+        let params = self
+            .ts_generics
+            .iter()
+            .flatten()
+            .map(|(ident, _)| Ty::named(&ts_ident(ident).to_string()))
+            .collect();
+        Ty::Ref(self.ts_name().to_string(), params)
+    }
+
+    /// the field's `#[ts(...)]` attributes
+    fn field_attrs(&self, field: &ast::Field<'a>) -> &Attrs {
+        let key = field.original as *const syn::Field;
+        self.field_attrs
+            .iter()
+            .find(|(f, _)| *f == key)
+            .map(|(_, attrs)| attrs)
+            .expect("field of the container")
+    }
+
+    fn field_context(&'a self, field: &'a ast::Field<'a>) -> FieldContext<'a> {
+        FieldContext {
+            attrs: self.field_attrs(field),
+            ctxt: self,
+            field,
+        }
+    }
+
+    fn field_to_ts(&self, field: &ast::Field<'a>) -> Ty {
+        let fc = self.field_context(field);
         // if user has provided a type ... use that
-        if attrs.ts_type.is_some() {
+        if let Some(ref lit) = fc.attrs.ts_type {
             use std::str::FromStr;
-            let s = attrs.ts_type.unwrap();
-            return match QuoteT::from_str(&s) {
-                Ok(tokens) => tokens,
-                Err(..) => {
-                    self.err_msg(&format!("{}: can't parse type {}", self.ident, s));
-                    quote!()
-                }
-            };
+            let s = lit.value();
+            if QuoteT::from_str(&s).is_err() {
+                self.err_msg(lit, &format!("{}: can't parse type {}", self.ident, s));
+            }
+            return Ty::Raw(s.trim().to_string());
         }
-
-        let fc = FieldContext {
-            attrs,
-            ctxt: &self,
-            field,
-        };
         if let Some(ref ty) = fc.attrs.ts_as {
             fc.type_to_ts(ty)
         } else {
-            fc.type_to_ts(&field.ty)
+            fc.type_to_ts(field.ty)
         }
     }
 
-    fn derive_field(&self, field: &ast::Field<'a>) -> QuoteT {
-        let field_name = field.attrs.name().serialize_name(); // use serde name instead of field.member
-        let field_name = ident_from_str(&field_name);
+    /// The key may be missing from the JSON, either because serde skips
+    /// it on output or because it has a default when deserializing
+    /// (or because we are told so with `#[ts(optional)]`).
+    fn is_optional(&self, field: &ast::Field<'a>) -> bool {
+        if field.attrs.skip_serializing_if().is_some() || self.field_attrs(field).optional {
+            return true;
+        }
+        // skip_deserializing sets a default on the field but it is still serialized
+        !field.attrs.skip_deserializing() && (self.default || !field.attrs.default().is_none())
+    }
 
-        let ty = self.field_to_ts(&field);
+    /// the `#[ts(rename)]` or serde name
+    fn field_name(&self, field: &ast::Field<'a>) -> String {
+        match self.field_attrs(field).rename {
+            Some(ref name) => name.clone(),
+            None => field.attrs.name().serialize_name(), // use serde name instead of field.member
+        }
+    }
+    /// names of the fields in the JSON, including any `#[ts(skip)]` ones
+    fn field_names(&self, fields: &[&ast::Field<'a>]) -> Vec<String> {
+        fields.iter().map(|f| self.field_name(f)).collect()
+    }
+    /// drop the `#[ts(skip)]` fields
+    fn filter_ts_skip(&self, fields: Vec<&'a ast::Field<'a>>) -> Vec<&'a ast::Field<'a>> {
+        fields
+            .into_iter()
+            .filter(|f| !self.field_attrs(f).skip)
+            .collect()
+    }
 
-        quote! {
-            #field_name: #ty
+    fn derive_field(&self, field: &ast::Field<'a>) -> Prop {
+        Prop {
+            key: self.field_name(field),
+            optional: self.is_optional(field),
+            readonly: self.field_attrs(field).readonly,
+            ty: self.field_to_ts(field),
+            doc: attrs::doc_comment(&field.original.attrs),
         }
     }
-    fn derive_fields(
-        &'a self,
-        fields: &'a [&'a ast::Field<'a>],
-    ) -> impl Iterator<Item = QuoteT> + 'a {
-        fields.iter().map(move |f| self.derive_field(f))
+    fn derive_fields(&self, fields: &[&ast::Field<'a>]) -> Vec<Prop> {
+        fields.iter().map(|f| self.derive_field(f)).collect()
     }
     fn derive_field_tuple(
         &'a self,
         fields: &'a [&'a ast::Field<'a>],
-    ) -> impl Iterator<Item = QuoteT> + 'a {
+    ) -> impl Iterator<Item = Ty> + 'a {
         fields.iter().map(move |f| self.field_to_ts(f))
     }
 
-    fn check_flatten(&self, fields: &[&'a ast::Field<'a>], ast_container: &ast::Container) -> bool {
-        let has_flatten = fields.iter().any(|f| f.attrs.flatten()); // .any(|f| f);
-        if has_flatten {
-            self.err_msg(&format!(
-                "{}: #[serde(flatten)] does not work for typescript-definitions.",
-                ast_container.ident
-            ));
-        };
-        has_flatten
+    /// `A & B & { ... } & { [key: string]: V }` where `A`, `B` and the map are
+    /// the `#[serde(flatten)]` fields and `{ ... }` (if any) holds the rest.
+    fn intersect_flatten(&self, flatten: &[&ast::Field<'a>], body: Option<Ty>) -> Ty {
+        let (maps, others): (Vec<&&ast::Field>, Vec<_>) =
+            flatten.iter().partition(|f| self.is_map(f));
+        let mut types = others
+            .iter()
+            .map(|f| self.field_to_ts(f))
+            .collect::<Vec<_>>();
+        types.extend(body);
+        types.extend(maps.iter().map(|f| self.field_to_ts(f)));
+        Ty::intersection(types)
+    }
+
+    fn is_map(&self, field: &ast::Field<'a>) -> bool {
+        let attrs = self.field_attrs(field);
+        if attrs.ts_type.is_some() {
+            return false;
+        }
+        match attrs.ts_as.as_ref().unwrap_or(field.ty) {
+            syn::Type::Path(syn::TypePath { path, .. }) => {
+                last_path_element(path).is_some_and(|ts| ts.is_map())
+            }
+            _ => false,
+        }
     }
 }
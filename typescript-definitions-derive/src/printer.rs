@@ -0,0 +1,637 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Printer
+//!
+//! Turns the `tsast` into typescript text. Parentheses are added
+//! where precedence requires them so builders never need to. The
+//! layout is controlled by a `Format`.
+
+use super::tsast::{Decl, Expr, Guard, Lit, Member, Prop, Stmt, Ty};
+use std::fmt;
+
+// typescript type precedence (loosest first)
+const TY_FN: u8 = 0;
+const TY_UNION: u8 = 1;
+const TY_INTERSECTION: u8 = 2;
+const TY_ARRAY: u8 = 3;
+const TY_ATOM: u8 = 4;
+
+// javascript operator precedence (loosest first)
+const EX_ARROW: u8 = 2;
+const EX_OR: u8 = 4;
+const EX_AND: u8 = 5;
+const EX_EQ: u8 = 10;
+const EX_ADD: u8 = 13;
+const EX_UNARY: u8 = 15;
+const EX_CALL: u8 = 18;
+const EX_ATOM: u8 = 20;
+
+/// Layout options, set crate-wide with the `TFY_FORMAT` environment
+/// variable and per type with `#[ts(format = "...")]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Format {
+    pub indent: usize,            // spaces per level
+    pub multiline: Option<usize>, // objects with more fields than this get one per line
+    pub comma: bool,              // separate properties with `,` instead of `;`
+    pub trailing_comma: bool,     // after the last of one-per-line fields and enum members
+    pub single_quote: bool,       // 'quote' instead of "quote" strings
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format {
+            indent: 2,
+            multiline: None,
+            comma: false,
+            trailing_comma: false,
+            single_quote: false,
+        }
+    }
+}
+
+impl Format {
+    /// Apply settings such as `"indent=4, multiline=3, separator=comma,
+    /// trailing_comma, quote=single"`.
+    pub fn update(&mut self, settings: &str) -> Result<(), String> {
+        for setting in settings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = match setting.find('=') {
+                Some(i) => (setting[..i].trim(), Some(setting[i + 1..].trim())),
+                None => (setting, None),
+            };
+            let number = || match value.map(str::parse::<usize>) {
+                Some(Ok(n)) => Ok(n),
+                _ => Err(format!("format: {} needs a number", key)),
+            };
+            match (key, value) {
+                ("indent", _) => self.indent = number()?,
+                ("multiline", _) => self.multiline = Some(number()?),
+                ("separator", Some("semicolon")) => self.comma = false,
+                ("separator", Some("comma")) => self.comma = true,
+                ("trailing_comma", None) | ("trailing_comma", Some("true")) => {
+                    self.trailing_comma = true
+                }
+                ("trailing_comma", Some("false")) => self.trailing_comma = false,
+                ("quote", Some("double")) => self.single_quote = false,
+                ("quote", Some("single")) => self.single_quote = true,
+                _ => return Err(format!("format: unknown setting \"{}\"", setting)),
+            }
+        }
+        Ok(())
+    }
+
+    /// print a top level declaration with this layout
+    pub fn decl(&self, decl: &Decl) -> String {
+        let mut p = Printer::new(self);
+        p.decl(decl);
+        p.finish()
+    }
+
+    fn is_multiline(&self, n: usize) -> bool {
+        self.multiline.is_some_and(|max| n > max)
+    }
+}
+
+pub struct Printer<'a> {
+    out: String,
+    indent: usize,
+    format: &'a Format,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(format: &'a Format) -> Self {
+        Printer {
+            out: String::new(),
+            indent: 0,
+            format,
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s)
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent * self.format.indent {
+            self.out.push(' ');
+        }
+    }
+
+    fn sep<T>(&mut self, items: &[T], sep: &str, mut f: impl FnMut(&mut Self, &T)) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.push(sep);
+            }
+            f(self, item);
+        }
+    }
+
+    pub fn decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Type { name, params, ty } => {
+                self.push("export type ");
+                self.push(name);
+                self.params(params);
+                self.push(" =");
+                match ty {
+                    // one variant per line
+                    Ty::Union(types) if types.iter().any(is_compound) => {
+                        self.indent += 1;
+                        for t in types {
+                            self.newline();
+                            let t = match t {
+                                Ty::Doc(doc, t) => {
+                                    self.jsdoc(doc);
+                                    self.newline();
+                                    t
+                                }
+                                t => t,
+                            };
+                            self.push("| ");
+                            self.ty(t, TY_INTERSECTION);
+                        }
+                        self.indent -= 1;
+                    }
+                    _ => {
+                        self.push(" ");
+                        self.ty(ty, TY_FN);
+                    }
+                }
+                self.push(";");
+            }
+            Decl::Enum {
+                name,
+                is_const,
+                members,
+            } => {
+                self.push(if *is_const {
+                    "export const enum "
+                } else {
+                    "export enum "
+                });
+                self.push(name);
+                self.push(" ");
+                let documented = members.iter().any(|m| m.doc.is_some());
+                self.members(members, ",", documented, |p, m| p.member(m));
+            }
+            Decl::Const { name, value } => {
+                self.push("export const ");
+                self.push(name);
+                self.push(" = ");
+                self.expr(value, EX_ARROW);
+                self.push(" as const;");
+            }
+            Decl::Guard(guard) => self.guard(guard),
+            Decl::Doc(doc, decl) => {
+                self.jsdoc(doc);
+                self.newline();
+                self.decl(decl);
+            }
+        }
+    }
+
+    /// `/** doc */` or, for several lines, a ` * ` prefixed block
+    fn jsdoc(&mut self, doc: &str) {
+        let doc = doc.replace("*/", "*\\/");
+        if !doc.contains('\n') {
+            self.push("/** ");
+            self.push(&doc);
+            self.push(" */");
+            return;
+        }
+        self.push("/**");
+        for line in doc.lines() {
+            self.newline();
+            self.push(" *");
+            if !line.is_empty() {
+                self.push(" ");
+                self.push(line);
+            }
+        }
+        self.newline();
+        self.push(" */");
+    }
+
+    fn params(&mut self, params: &[String]) {
+        if !params.is_empty() {
+            self.push("<");
+            self.sep(params, ", ", |p, t| p.push(t));
+            self.push(">");
+        }
+    }
+
+    fn guard(&mut self, guard: &Guard) {
+        self.push("export const ");
+        self.push(&guard.name);
+        self.push(" = ");
+        self.params(&guard.params);
+        self.push("(");
+        self.push(&guard.arg);
+        self.push(": any");
+        if guard.typename {
+            self.push(", typename: string");
+        }
+        self.push("): ");
+        self.push(&guard.arg);
+        self.push(" is ");
+        self.ty(&guard.ty, TY_FN);
+        self.push(" => ");
+        self.block(&guard.body);
+        self.push(";");
+    }
+
+    pub fn ty(&mut self, ty: &Ty, min: u8) {
+        let prec = ty_prec(ty);
+        if prec < min {
+            self.push("(");
+        }
+        match ty {
+            Ty::Ref(name, args) => {
+                self.push(name);
+                if !args.is_empty() {
+                    self.push("<");
+                    self.sep(args, ", ", |p, t| p.ty(t, TY_FN));
+                    self.push(">");
+                }
+            }
+            Ty::Lit(lit) => self.lit(lit),
+            Ty::Raw(s) => self.push(s),
+            Ty::Array(elem) => {
+                self.ty(elem, TY_ARRAY);
+                self.push("[]");
+            }
+            Ty::Tuple(elems) => {
+                self.push("[");
+                self.sep(elems, ", ", |p, t| p.ty(t, TY_FN));
+                self.push("]");
+            }
+            Ty::Object(props) => {
+                let sep = if self.format.comma { "," } else { ";" };
+                let documented = props.iter().any(|p| p.doc.is_some());
+                self.members(props, sep, documented, |p, prop| p.prop(prop));
+            }
+            Ty::Index(k, v) => {
+                self.push("{ [key: ");
+                self.ty(k, TY_FN);
+                self.push("]: ");
+                self.ty(v, TY_FN);
+                self.push(" }");
+            }
+            Ty::Union(types) => {
+                if types.is_empty() {
+                    self.push("never");
+                }
+                self.sep(types, " | ", |p, t| p.ty(t, TY_INTERSECTION));
+            }
+            Ty::Intersection(types) => {
+                if types.is_empty() {
+                    self.push("unknown");
+                }
+                self.sep(types, " & ", |p, t| p.ty(t, TY_ARRAY));
+            }
+            Ty::Fn(args, ret) => {
+                self.push("(");
+                self.sep(args, ", ", |p, (name, t)| {
+                    p.push(name);
+                    p.push(": ");
+                    p.ty(t, TY_FN);
+                });
+                self.push(") => ");
+                self.ty(ret, TY_FN);
+            }
+            Ty::Doc(doc, t) => {
+                self.jsdoc(doc);
+                self.push(" ");
+                self.ty(t, TY_FN);
+            }
+        }
+        if prec < min {
+            self.push(")");
+        }
+    }
+
+    /// `{ a; b }` or, if there are too many or they are documented, one per line
+    fn members<T>(
+        &mut self,
+        items: &[T],
+        sep: &str,
+        documented: bool,
+        mut f: impl FnMut(&mut Self, &T),
+    ) {
+        if items.is_empty() {
+            self.push("{}");
+        } else if documented || self.format.is_multiline(items.len()) {
+            // typescript always ends a member with a `;`
+            let last = sep == ";" || self.format.trailing_comma;
+            self.push("{");
+            self.indent += 1;
+            for (i, item) in items.iter().enumerate() {
+                self.newline();
+                f(self, item);
+                if i + 1 < items.len() || last {
+                    self.push(sep);
+                }
+            }
+            self.indent -= 1;
+            self.newline();
+            self.push("}");
+        } else {
+            self.push("{ ");
+            self.sep(items, &format!("{} ", sep), f);
+            self.push(" }");
+        }
+    }
+
+    fn member(&mut self, member: &Member) {
+        if let Some(ref doc) = member.doc {
+            self.jsdoc(doc);
+            self.newline();
+        }
+        self.push(&member.key);
+        self.push(" = ");
+        self.lit(&member.value);
+    }
+
+    fn prop(&mut self, prop: &Prop) {
+        if let Some(ref doc) = prop.doc {
+            self.jsdoc(doc);
+            self.newline();
+        }
+        if prop.readonly {
+            self.push("readonly ");
+        }
+        self.key(&prop.key);
+        if prop.optional {
+            self.push("?");
+        }
+        self.push(": ");
+        self.ty(&prop.ty, TY_FN);
+    }
+
+    fn key(&mut self, key: &str) {
+        if is_ident(key) {
+            self.push(key)
+        } else {
+            self.string(key)
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        let s = quote_str(s, self.format.single_quote);
+        self.push(&s)
+    }
+
+    fn lit(&mut self, lit: &Lit) {
+        match lit {
+            Lit::Str(s) => self.string(s),
+            Lit::Num(n) => self.push(n),
+        }
+    }
+
+    pub fn expr(&mut self, expr: &Expr, min: u8) {
+        let prec = expr_prec(expr);
+        if prec < min {
+            self.push("(");
+        }
+        match expr {
+            Expr::Ident(name) => self.push(name),
+            Expr::Lit(lit) => self.lit(lit),
+            Expr::Member(obj, key) => {
+                self.expr(obj, EX_CALL);
+                if is_ident(key) {
+                    self.push(".");
+                    self.push(key);
+                } else {
+                    self.push("[");
+                    self.string(key);
+                    self.push("]");
+                }
+            }
+            Expr::Index(obj, idx) => {
+                self.expr(obj, EX_CALL);
+                self.push("[");
+                self.expr(idx, 0);
+                self.push("]");
+            }
+            Expr::Call(func, types, args) => {
+                self.expr(func, EX_CALL);
+                if !types.is_empty() {
+                    self.push("<");
+                    self.sep(types, ", ", |p, t| p.ty(t, TY_FN));
+                    self.push(">");
+                }
+                self.push("(");
+                self.sep(args, ", ", |p, e| p.expr(e, EX_ARROW));
+                self.push(")");
+            }
+            Expr::Arrow(args, body) => {
+                self.push("(");
+                self.sep(args, ", ", |p, a| p.push(a));
+                self.push(") => ");
+                self.block(body);
+            }
+            Expr::Array(elems) => {
+                self.push("[");
+                self.sep(elems, ", ", |p, e| p.expr(e, EX_ARROW));
+                self.push("]");
+            }
+            Expr::Unary(op, e) => {
+                self.push(op);
+                if op.chars().all(char::is_alphabetic) {
+                    self.push(" ");
+                }
+                self.expr(e, EX_UNARY);
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                self.expr(lhs, prec);
+                self.push(" ");
+                self.push(op);
+                self.push(" ");
+                self.expr(rhs, prec + 1);
+            }
+        }
+        if prec < min {
+            self.push(")");
+        }
+    }
+
+    /// `{ ... }` with one statement per line
+    fn block(&mut self, stmts: &[Stmt]) {
+        if stmts.is_empty() {
+            self.push("{}");
+            return;
+        }
+        self.push("{");
+        self.indent += 1;
+        for s in stmts {
+            self.newline();
+            self.stmt(s);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.push("}");
+    }
+
+    pub fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::If(cond, then) => {
+                self.push("if (");
+                self.expr(cond, 0);
+                self.push(") ");
+                self.stmt(then);
+            }
+            Stmt::Return(e) => {
+                self.push("return ");
+                self.expr(e, 0);
+                self.push(";");
+            }
+            Stmt::Const(name, e) | Stmt::Let(name, e) => {
+                self.push(if let Stmt::Const(..) = stmt {
+                    "const "
+                } else {
+                    "let "
+                });
+                self.push(name);
+                self.push(" = ");
+                self.expr(e, EX_ARROW);
+                self.push(";");
+            }
+            Stmt::Omit(name, key, e) => {
+                self.push("const { ");
+                self.key(key);
+                self.push(": _, ...");
+                self.push(name);
+                self.push(" } = ");
+                self.expr(e, EX_ARROW);
+                self.push(";");
+            }
+            Stmt::ForOf(var, e, body) | Stmt::ForIn(var, e, body) => {
+                self.push("for (let ");
+                self.push(var);
+                self.push(if let Stmt::ForOf(..) = stmt {
+                    " of "
+                } else {
+                    " in "
+                });
+                self.expr(e, 0);
+                self.push(") ");
+                self.block(body);
+            }
+            Stmt::Block(body) => self.block(body),
+            Stmt::Continue => self.push("continue;"),
+            Stmt::Break => self.push("break;"),
+        }
+    }
+}
+
+/// unions of these are written one per line
+fn is_compound(ty: &Ty) -> bool {
+    matches!(
+        ty,
+        Ty::Object(..) | Ty::Index(..) | Ty::Tuple(..) | Ty::Intersection(..) | Ty::Doc(..)
+    )
+}
+
+fn ty_prec(ty: &Ty) -> u8 {
+    match ty {
+        Ty::Fn(..) | Ty::Raw(..) => TY_FN,
+        Ty::Union(t) if t.len() > 1 => TY_UNION,
+        Ty::Intersection(t) if t.len() > 1 => TY_INTERSECTION,
+        Ty::Array(..) => TY_ARRAY,
+        Ty::Doc(_, t) => ty_prec(t),
+        _ => TY_ATOM,
+    }
+}
+
+fn expr_prec(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Arrow(..) => EX_ARROW,
+        Expr::Binary(_, op, _) => match *op {
+            "||" => EX_OR,
+            "&&" => EX_AND,
+            "+" | "-" => EX_ADD,
+            _ => EX_EQ,
+        },
+        Expr::Unary(..) => EX_UNARY,
+        Expr::Member(..) | Expr::Index(..) | Expr::Call(..) => EX_CALL,
+        _ => EX_ATOM,
+    }
+}
+
+/// A property name that can be written bare i.e. `a: number` or `obj.a`
+/// rather than `"content-type": string` or `obj["content-type"]`.
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            s != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// a quoted javascript string
+fn quote_str(s: &str, single: bool) -> String {
+    let quote = if single { '\'' } else { '"' };
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+    for c in s.chars() {
+        match c {
+            c if c == quote => {
+                out.push('\\');
+                out.push(c)
+            }
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+impl fmt::Display for Decl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Format::default().decl(self))
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = Format::default();
+        let mut p = Printer::new(&format);
+        p.ty(self, TY_FN);
+        f.write_str(&p.finish())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = Format::default();
+        let mut p = Printer::new(&format);
+        p.expr(self, 0);
+        f.write_str(&p.finish())
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = Format::default();
+        let mut p = Printer::new(&format);
+        p.stmt(self);
+        f.write_str(&p.finish())
+    }
+}
@@ -7,6 +7,14 @@ mod macro_test {
     use super::Typescriptify;
     use insta::assert_snapshot_matches;
     use quote::quote;
+
+    fn messages(errors: &[syn::Error]) -> String {
+        errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
     #[test]
     fn tag_clash_in_enum() {
         let tokens = quote!(
@@ -18,34 +26,1383 @@ mod macro_test {
             }
         );
 
-        let result = std::panic::catch_unwind(move || Typescriptify::parse(true, tokens));
+        let result = Typescriptify::parse(true, tokens);
         match result {
-            Ok(_x) => assert!(false, "expecting panic!"),
-            Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
-            @r###"2 errors:
-	# variant field name `kind` conflicts with internal tag
-	# clash with field in "A::B". Maybe use a #[serde(content="...")] attribute."###
+            Ok(_x) => panic!("expecting errors!"),
+            Err(ref errors) => assert_snapshot_matches!(messages(errors),
+            @r###"variant field name `kind` conflicts with internal tag
+clash with field in "A::B". Maybe use a #[serde(content="...")] attribute."###
             ),
         }
     }
     #[test]
-    fn flatten_is_fail() {
+    fn flatten_is_intersection() {
         let tokens = quote!(
             #[derive(Serialize)]
             struct SSS {
                 a: i32,
-                b: f64,
                 #[serde(flatten)]
                 c: DDD,
             }
         );
-        let result = std::panic::catch_unwind(move || Typescriptify::parse(true, tokens));
-        match result {
-            Ok(_x) => assert!(false, "expecting panic!"),
-            Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
-            @"SSS: #[serde(flatten)] does not work for typescript-definitions."
-            ),
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export type SSS = DDD & { a: number };"
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isSSS = (obj: any): obj is SSS => {
+  if (obj == undefined) return false;
+  if (!isDDD(obj)) return false;
+  if (obj.a === undefined) return false;
+  {
+    const val = obj.a;
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn flatten_map_is_index_signature() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct S {
+                id: i32,
+                #[serde(flatten)]
+                extra: HashMap<String, bool>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export type S = { id: number } & { [key: string]: boolean };"
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj === null || !(typeof obj === "object")) return false;
+  for (let k in obj) {
+    if (k === "id") continue;
+    let v = obj[k];
+    if (!(typeof v === "boolean")) return false;
+  }
+  if (obj.id === undefined) return false;
+  {
+    const val = obj.id;
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn flatten_map_with_flatten_struct() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct S {
+                id: u32,
+                #[serde(flatten)]
+                base: Base,
+                #[serde(flatten)]
+                extra: HashMap<String, u32>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type S = Base & { id: number } & { [key: string]: number };");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (!isBase(obj)) return false;
+  if (obj.id === undefined) return false;
+  {
+    const val = obj.id;
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###);
+    }
+
+    #[test]
+    fn flatten_map_in_struct_variant() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            enum E {
+                V {
+                    id: u32,
+                    #[serde(flatten)]
+                    extra: HashMap<String, HashMap<String, u32>>,
+                },
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    const v = obj.V;
+    if (v == undefined) return false;
+    if (v === null || !(typeof v === "object")) return false;
+    for (let k1 in v) {
+      if (k1 === "id") continue;
+      let v1 = v[k1];
+      if (v1 === null || !(typeof v1 === "object")) return false;
+      for (let k2 in v1) {
+        let v2 = v1[k2];
+        if (!(typeof v2 === "number")) return false;
+      }
+    }
+    if (v.id === undefined) return false;
+    {
+      const val = v.id;
+      if (!(typeof val === "number")) return false;
+    }
+    return true;
+  })()) return true;
+  return false;
+};"###);
+    }
+
+    #[test]
+    fn internally_tagged_newtype_is_intersection() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "kind")]
+            enum E {
+                V(Inner),
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type E =
+  | { kind: "V" } & Inner;"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj.kind === "V")) return false;
+    {
+      const { kind: _, ...rest } = obj;
+      if (!isInner(rest)) return false;
+    }
+    return true;
+  })()) return true;
+  return false;
+};"###
+        );
+    }
+
+    #[test]
+    fn struct_tag_is_literal_property() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "type")]
+            struct Ping {
+                id: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type Ping = { type: "Ping"; id: number };"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isPing = (obj: any): obj is Ping => {
+  if (obj == undefined) return false;
+  if (!(obj.type === "Ping")) return false;
+  if (obj.id === undefined) return false;
+  {
+    const val = obj.id;
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn into_uses_proxy_type() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(into = "Proxy", try_from = "Raw")]
+            struct S {
+                a: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export type S = Proxy;"
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (!isProxy(obj)) return false;
+  return true;
+};"###
+        );
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(into = "Proxy", try_from = "Raw")]
+            #[ts(deserialize)]
+            struct S {
+                a: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export type S = Raw;"
+        );
+    }
+
+    #[test]
+    fn other_and_untagged_variants() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "kind")]
+            enum E {
+                A { a: i32 },
+                #[serde(untagged)]
+                Raw(String),
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type E =
+  | { kind: "A"; a: number }
+  | string;"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj.kind === "A")) return false;
+    if (obj.a === undefined) return false;
+    {
+      const val = obj.a;
+      if (!(typeof val === "number")) return false;
+    }
+    return true;
+  })()) return true;
+  if ((() => {
+    if (!(typeof obj === "string")) return false;
+    return true;
+  })()) return true;
+  return false;
+};"###
+        );
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "kind")]
+            enum E {
+                A { a: i32 },
+                #[serde(other)]
+                Unknown,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type E =
+  | { kind: "A"; a: number }
+  | { kind: string };"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj.kind === "A")) return false;
+    if (obj.a === undefined) return false;
+    {
+      const val = obj.a;
+      if (!(typeof val === "number")) return false;
+    }
+    return true;
+  })()) return true;
+  if ((() => {
+    return typeof obj.kind === "string";
+  })()) return true;
+  return false;
+};"###
+        );
+    }
+
+    #[test]
+    fn repr_number_is_number_enum() {
+        let tokens = quote!(
+            #[derive(Serialize_repr)]
+            #[repr(i8)]
+            #[ts(repr = "number")]
+            enum Color {
+                Red = 1,
+                Green,
+                Blue = -4,
+                Black,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export enum Color { Red = 1, Green = 2, Blue = -4, Black = -3 }"
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isColor = (obj: any): obj is Color => {
+  if (!(obj === 1 || obj === 2 || obj === -4 || obj === -3)) return false;
+  return true;
+};"###
+        );
+
+        let tokens = quote!(
+            #[derive(Serialize_repr)]
+            #[repr(u64)]
+            #[ts(repr = "number")]
+            enum Big {
+                Max = 18446744073709551615,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export enum Big { Max = 18446744073709551615 }");
+    }
+
+    #[test]
+    fn number_enum_errors() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(repr = "number")]
+            enum Color {
+                Red = 1,
+                Black = LAST,
+                White,
+            }
+        );
+        let errors = Typescriptify::parse(false, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @"Black: discriminant `LAST` must be an integer literal to be a typescript number");
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(repr = "number")]
+            enum E {
+                A,
+                B(i32),
+            }
+        );
+        let errors = Typescriptify::parse(false, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @"B: only unit variants can be serialized as numbers");
+
+        let tokens = quote!(
+            #[derive(Serialize_repr)]
+            #[repr(u8)]
+            enum Color {
+                Red = 1,
+                Green,
+            }
+        );
+        let errors = Typescriptify::parse(false, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @r###"Color: add #[ts(repr = "number")] if this enum is serialized as its discriminants (e.g. with serde_repr) or #[ts(repr = "string")] if not"###);
+
+        // without discriminants (or with an explicit choice) there's nothing to decide
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[repr(u8)]
+            enum Color {
+                Red,
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum Color { Red = "Red", Green = "Green" }"###);
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[repr(u8)]
+            #[ts(repr = "string")]
+            enum Color {
+                Red = 1,
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum Color { Red = "Red", Green = "Green" }"###);
+    }
+
+    #[test]
+    fn unit_enum_keys_are_rust_idents() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(rename_all = "kebab-case")]
+            enum Color {
+                DarkRed,
+                r#Type,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export enum Color { DarkRed = "dark-red", Type = "type" }"###
+        );
+    }
+
+    #[test]
+    fn unit_enum_as_union() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(enum_style = "union", enum_values)]
+            enum Color {
+                Red,
+                #[serde(rename = "green")]
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type Color = "Red" | "green";
+export const ColorValues = ["Red", "green"] as const;"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isColor = (obj: any): obj is Color => {
+  if (!(obj === "Red" || obj === "green")) return false;
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn quoted_property_names() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct S {
+                #[serde(rename = "content-type")]
+                content_type: String,
+                #[serde(rename = "@id")]
+                id: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type S = { "content-type": string; "@id": number };"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj["content-type"] === undefined) return false;
+  {
+    const val = obj["content-type"];
+    if (!(typeof val === "string")) return false;
+  }
+  if (obj["@id"] === undefined) return false;
+  {
+    const val = obj["@id"];
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###
+        );
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "$type", content = "2fa", rename_all = "kebab-case")]
+            enum E {
+                DarkRed(i32),
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @r###"export type E =
+  | { "$type": "dark-red"; "2fa": number };"###
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj["$type"] === "dark-red")) return false;
+    const val = obj["2fa"];
+    if (val === undefined) return false;
+    if (!(typeof val === "number")) return false;
+    return true;
+  })()) return true;
+  return false;
+};"###
+        );
+    }
+
+    #[test]
+    fn reserved_names_are_escaped() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct Value<number> {
+                value: number,
+                other: r#delete,
+                cb: fn(class: i32, _: i32, _: bool),
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export type Value<number_> = { value: number_; other: delete_; cb: (class_: number, _dummy1: number, _dummy2: boolean) => undefined };"
+        );
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isValue = <number_>(obj: any, typename: string): obj is Value<number_> => {
+  if (obj == undefined) return false;
+  if (obj.value === undefined) return false;
+  {
+    const val = obj.value;
+    if (!isValue__number_<number_>(val, typename)) return false;
+  }
+  if (obj.other === undefined) return false;
+  {
+    const val = obj.other;
+    if (!isdelete_(val)) return false;
+  }
+  if (obj.cb === undefined) return false;
+  {
+    const val = obj.cb;
+  }
+  return true;
+};
+export const isValue__number_ = <number_>(val: any, typename: string): val is number_ => {
+  return typeof val === typename;
+};"###
+        );
+    }
+
+    #[test]
+    fn printer_adds_parentheses() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct S {
+                a: Vec<Option<i32>>,
+                b: Option<fn(i32)>,
+                c: (Vec<i32>, [bool; 2]),
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(),
+        @"export type S = { a: (number | null)[]; b: ((_dummy0: number) => undefined) | null; c: [number[], boolean[]] };"
+        );
+    }
+
+    #[test]
+    fn format_options() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(format = "indent=4, multiline=1, separator=comma, trailing_comma, quote=single")]
+            struct S {
+                a: i32,
+                #[serde(rename = "it's")]
+                b: Option<String>,
+                c: Vec<(i32, String)>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export type S = {
+    a: number,
+    'it\'s': string | null,
+    c: [number, string][],
+};"###);
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isS = (obj: any): obj is S => {
+    if (obj == undefined) return false;
+    if (obj.a === undefined) return false;
+    {
+        const val = obj.a;
+        if (!(typeof val === 'number')) return false;
+    }
+    if (obj['it\'s'] === undefined) return false;
+    {
+        const val = obj['it\'s'];
+        if (!(val === null)) {
+            if (!(typeof val === 'string')) return false;
+        }
+    }
+    if (obj.c === undefined) return false;
+    {
+        const val = obj.c;
+        if (!Array.isArray(val)) return false;
+        for (let x of val) {
+            if (!Array.isArray(x) || !(x.length === 2)) return false;
+            {
+                {
+                    const val = x[0];
+                    if (val === undefined) return false;
+                    if (!(typeof val === 'number')) return false;
+                }
+                {
+                    const val = x[1];
+                    if (val === undefined) return false;
+                    if (!(typeof val === 'string')) return false;
+                }
+            }
+        }
+    }
+    return true;
+};"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(format = "multiline=2")]
+            enum E {
+                A,
+                B,
+                C,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum E {
+  A = "A",
+  B = "B",
+  C = "C"
+}"###);
+    }
+
+    #[test]
+    fn doc_comments_become_jsdoc() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            /// A shape.
+            ///
+            /// See `Point`:
+            ///
+            ///     let p = Point { x: 1 };
+            enum Shape {
+                /// Just a point
+                Point {
+                    /// distance along */
+                    x: i32,
+                    y: i32,
+                },
+                Line(i32, i32),
+                /// Is a circle
+                Circle(f64),
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"/**
+ * A shape.
+ *
+ * See `Point`:
+ *
+ *     let p = Point { x: 1 };
+ */
+export type Shape =
+  /** Just a point */
+  | { Point: {
+    /** distance along *\/ */
+    x: number;
+    y: number;
+  } }
+  | { Line: [number, number] }
+  /** Is a circle */
+  | { Circle: number };"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            enum Color {
+                /// the red one
+                Red,
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum Color {
+  /** the red one */
+  Red = "Red",
+  Green = "Green"
+}"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(enum_style = "union")]
+            enum Color {
+                /// the red one
+                Red,
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export type Color =
+  /** the red one */
+  | "Red"
+  | "Green";"###);
+
+        // a non-breaking space isn't indentation
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[doc = "\u{a0}x"]
+            /// y
+            struct S {
+                a: i32,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"/**
+ *  x
+ *  y
+ */
+export type S = { a: number };"###);
+    }
+
+    #[test]
+    fn jsdoc_tags() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            /// A user.
+            #[ts(example = "const u: User = { id: 1, name: \"x\", nick: null };")]
+            #[ts(see = "https://example.com/users")]
+            struct User {
+                id: i32,
+                #[deprecated(since = "0.2", note = "use `nick`")]
+                name: String,
+                /// shown instead of the name
+                #[deprecated]
+                nick: Option<String>,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"/**
+ * A user.
+ * @example
+ * const u: User = { id: 1, name: "x", nick: null };
+ * @see https://example.com/users
+ */
+export type User = {
+  id: number;
+  /** @deprecated since 0.2: use `nick` */
+  name: string;
+  /**
+   * shown instead of the name
+   * @deprecated
+   */
+  nick: string | null;
+};"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            enum Color {
+                #[deprecated = "too bright"]
+                Red,
+                #[ts(see = "Color.Red")]
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum Color {
+  /** @deprecated too bright */
+  Red = "Red",
+  /** @see Color.Red */
+  Green = "Green"
+}"###);
+    }
+
+    #[test]
+    fn variant_attributes() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "kind")]
+            enum Shape {
+                #[ts(rename = "point")]
+                Point { x: i32, y: i32 },
+                #[ts(skip)]
+                Internal { secret: String },
+                #[ts(guard = false)]
+                Foreign { data: Opaque },
+                #[ts(ts_type = "{ kind: string, points: number[] }")]
+                Line { from: Point, to: Point },
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export type Shape =
+  | { kind: "point"; x: number; y: number }
+  | { kind: "Foreign"; data: Opaque }
+  | ({ kind: string, points: number[] });"###);
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isShape = (obj: any): obj is Shape => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj.kind === "point")) return false;
+    if (obj.x === undefined) return false;
+    {
+      const val = obj.x;
+      if (!(typeof val === "number")) return false;
+    }
+    if (obj.y === undefined) return false;
+    {
+      const val = obj.y;
+      if (!(typeof val === "number")) return false;
+    }
+    return true;
+  })()) return true;
+  if ((() => {
+    return obj.kind === "Foreign";
+  })()) return true;
+  if ((() => {
+    {
+      if (!(typeof obj === "object")) return false;
+      {
+        const val1 = obj.kind;
+        if (!(typeof val1 === "string")) return false;
+      }
+      {
+        const val1 = obj.points;
+        const val2 = val1;
+        {
+          if (!Array.isArray(val2)) return false;
+          for (let val1 of val2) {
+            if (!(typeof val1 === "number")) return false;
+          }
         }
+      }
+      return true;
+    }
+    return true;
+  })()) return true;
+  return false;
+};"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            enum Color {
+                #[ts(rename = "red")]
+                Red,
+                #[ts(skip)]
+                Green,
+                Blue,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum Color { Red = "red", Blue = "Blue" }"###);
+    }
+
+    #[test]
+    fn field_attributes() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(deny_unknown_fields)]
+            struct S {
+                #[ts(readonly)]
+                id: i32,
+                #[ts(skip)]
+                debug: String,
+                #[ts(rename = "displayName")]
+                name: String,
+                #[ts(optional)]
+                stripped: Vec<i32>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type S = { readonly id: number; displayName: string; stripped?: number[] };");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj.id === undefined) return false;
+  {
+    const val = obj.id;
+    if (!(typeof val === "number")) return false;
+  }
+  if (obj.displayName === undefined) return false;
+  {
+    const val = obj.displayName;
+    if (!(typeof val === "string")) return false;
+  }
+  if (!(obj.stripped === undefined)) {
+    const val = obj.stripped;
+    if (!Array.isArray(val)) return false;
+    for (let x of val) {
+      if (!(typeof x === "number")) return false;
+    }
+  }
+  for (let k in obj) {
+    if (!(k === "id" || k === "debug" || k === "displayName" || k === "stripped")) return false;
+  }
+  return true;
+};"###);
+    }
+
+    #[test]
+    fn renamed_container() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(rename = "ServerConfig")]
+            struct Config<T> {
+                value: T,
+            }
+        );
+        let q = crate::do_derive_typescript_definition(tokens.clone()).to_string();
+        assert!(q.contains("TS_EXPORT_SERVERCONFIG"));
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type ServerConfig<T> = { value: T };");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isServerConfig = <T>(obj: any, typename: string): obj is ServerConfig<T> => {
+  if (obj == undefined) return false;
+  if (obj.value === undefined) return false;
+  {
+    const val = obj.value;
+    if (!isServerConfig__T<T>(val, typename)) return false;
+  }
+  return true;
+};
+export const isServerConfig__T = <T>(val: any, typename: string): val is T => {
+  return typeof val === typename;
+};"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(rename = "Hue", enum_values)]
+            enum Color {
+                Red,
+                Green,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export enum Hue { Red = "Red", Green = "Green" }
+export const HueValues = [Hue.Red, Hue.Green] as const;"###);
+    }
+
+    #[test]
+    fn errors_become_compile_errors() {
+        let tokens = quote!(
+            #[derive(TypeScriptify)]
+            struct S {
+                #[ts(ts_type = "{ a: number")]
+                a: i32,
+                #[ts(readonly = "yes")]
+                b: String,
+            }
+        );
+        let q = crate::do_derive_type_script_ify(tokens.clone()).to_string();
+        assert_eq!(q.matches("compile_error").count(), 2);
+        assert!(!q.contains("impl"));
+        let errors = Typescriptify::parse(false, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @r###"unsupported option: readonly = "yes"
+S: can't parse type { a: number"###);
+    }
+
+    #[test]
+    fn ts_guard_grammar_errors() {
+        let tokens = quote!(
+            #[derive(TypeScriptify)]
+            struct S {
+                #[ts(ts_guard = "{[key: string number]: number}")]
+                a: HashMap<String, i32>,
+                #[ts(ts_guard = "{ a: number, }")]
+                b: A,
+                #[ts(ts_type = "number | ")]
+                c: i32,
+                #[ts(ts_guard = "[numbr, string]")]
+                d: (i32, String),
+                #[ts(ts_type = "{ a: number[")]
+                e: A,
+            }
+        );
+        let errors = Typescriptify::parse(true, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @r###"S: can't parse type { a: number[
+can't parse "{[key: string number]: number}": expected `]`, found `number` at column 15
+can't parse "{ a: number, }": expected a field name, found `}` at column 14
+can't parse "number | ": expected a type, found end of string at column 10
+can't parse "[numbr, string]": expected a type, found `numbr` at column 2"###);
+        let tokens = quote!(
+            #[derive(TypeScriptify)]
+            enum E {
+                #[ts(ts_type = "{ kind: string\n  points number[] }")]
+                Line { points: Vec<i32> },
+            }
+        );
+        let errors = Typescriptify::parse(true, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @r###"can't parse "{ kind: string\n  points number[] }": expected `,`, `[]`, `|` or `}`, found `points` at line 2, column 3"###);
+    }
+
+    #[test]
+    fn strict_rejects_unmappable_types() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(strict)]
+            struct S<T: Tr> {
+                a: *const u8,
+                b: fn(i32) -> i32,
+                c: Box<dyn Fn(i32)>,
+                d: serde_json::Value,
+                e: <T as Tr>::Out,
+                f: m!(),
+                g: crate::a::B,
+                h: Vec<A>,
+                #[ts(ts_type = "any")]
+                i: serde_json::Value,
+                k: models::User,
+                #[ts(ts_as = "Vec<u8>")]
+                j: *const u8,
+            }
+        );
+        let errors = Typescriptify::parse(false, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @r###"S: can't map raw pointer `* const u8` to typescript, use #[ts(ts_as = "...")] or #[ts(ts_type = "...")]
+S: can't map function `fn ( i32 ) -> i32` to typescript, use #[ts(ts_as = "...")] or #[ts(ts_type = "...")]
+S: can't map closure `Fn` to typescript, use #[ts(ts_as = "...")] or #[ts(ts_type = "...")]
+S: can't map associated type `< T as Tr > :: Out` to typescript, use #[ts(ts_as = "...")] or #[ts(ts_type = "...")]
+S: can't map type `m ! ( )` to typescript, use #[ts(ts_as = "...")] or #[ts(ts_type = "...")]"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(strict = "unknown")]
+            struct S<T: Tr> {
+                a: *const u8,
+                b: fn(i32) -> i32,
+                d: serde_json::Value,
+                e: <T as Tr>::Out,
+                g: crate::a::B,
+                k: models::User,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type S<T> = { a: unknown; b: unknown; d: Value; e: unknown; g: B; k: User };");
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(strict = "maybe")]
+            struct S {
+                a: i32,
+            }
+        );
+        let errors = Typescriptify::parse(false, tokens).err().unwrap();
+        assert_snapshot_matches!(messages(&errors), @r###"S: strict value must be true, false or "unknown" not "maybe""###);
+    }
+
+    #[test]
+    fn referenced_types_are_asserted() {
+        let tokens = quote!(
+            #[derive(TypeScriptify)]
+            struct S<'a, T> {
+                a: i32,
+                b: B<f64>,
+                c: Vec<C<'a, T>>,
+                d: T,
+                e: serde_json::Value,
+                #[ts(unchecked)]
+                f: D,
+                #[ts(ts_as = "E")]
+                g: X,
+                h: Option<crate::m::F>,
+                #[ts(ts_type = "string")]
+                i: G,
+                j: models::User,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert_snapshot_matches!(ty.assert_referenced().to_string(), @"const _ : ( ) = { fn _assert < T : ? Sized + :: typescript_definitions :: TypeScriptifyTrait > ( ) { } impl < 'a , T > S < 'a , T > { # [ allow ( dead_code ) ] fn _assert_referenced ( ) { _assert :: < B < f64 > > ( ) ; _assert :: < C < 'a , T > > ( ) ; _assert :: < serde_json :: Value > ( ) ; _assert :: < crate :: m :: F > ( ) ; _assert :: < models :: User > ( ) ; } } } ;");
+
+        let tokens = quote!(
+            #[derive(TypeScriptify)]
+            struct S {
+                a: i32,
+                b: Vec<String>,
+            }
+        );
+        let ty = Typescriptify::parse(false, tokens).unwrap();
+        assert!(ty.assert_referenced().is_empty());
+    }
+
+    #[test]
+    fn self_is_the_container() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct Tree {
+                value: i32,
+                children: Vec<Box<Self>>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type Tree = { value: number; children: Tree[] };");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isTree = (obj: any): obj is Tree => {
+  if (obj == undefined) return false;
+  if (obj.value === undefined) return false;
+  {
+    const val = obj.value;
+    if (!(typeof val === "number")) return false;
+  }
+  if (obj.children === undefined) return false;
+  {
+    const val = obj.children;
+    if (!Array.isArray(val)) return false;
+    for (let x of val) {
+      if (!isTree(x)) return false;
+    }
+  }
+  return true;
+};"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(rename = "Node")]
+            struct Tree<T> {
+                value: T,
+                next: Option<Box<Self>>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type Node<T> = { value: T; next: Node<T> | null };");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isNode = <T>(obj: any, typename: string): obj is Node<T> => {
+  if (obj == undefined) return false;
+  if (obj.value === undefined) return false;
+  {
+    const val = obj.value;
+    if (!isNode__T<T>(val, typename)) return false;
+  }
+  if (obj.next === undefined) return false;
+  {
+    const val = obj.next;
+    if (!(val === null)) {
+      if (!isNode<T>(val, typename)) return false;
+    }
+  }
+  return true;
+};
+export const isNode__T = <T>(val: any, typename: string): val is T => {
+  return typeof val === typename;
+};"###);
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            enum List {
+                Cons(i32, Box<Self>),
+                Nil,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @r###"export type List =
+  | { Cons: [number, List] }
+  | "Nil";"###);
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isList = (obj: any): obj is List => {
+  if (obj == undefined) return false;
+  if ((() => {
+    const v = obj.Cons;
+    if (!Array.isArray(v) || !(v.length === 2)) return false;
+    if (v[0] === undefined) return false;
+    {
+      const val = v[0];
+      if (!(typeof val === "number")) return false;
+    }
+    if (v[1] === undefined) return false;
+    {
+      const val = v[1];
+      if (!isList(val)) return false;
+    }
+    return true;
+  })()) return true;
+  if ((() => {
+    return obj === "Nil";
+  })()) return true;
+  return false;
+};"###);
+    }
+
+    #[test]
+    fn internally_tagged_newtype_guard_omits_tag() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "type-of")]
+            enum E {
+                V(Inner),
+                W { x: i32 },
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj["type-of"] === "V")) return false;
+    {
+      const { "type-of": _, ...rest } = obj;
+      if (!isInner(rest)) return false;
+    }
+    return true;
+  })()) return true;
+  if ((() => {
+    if (!(obj["type-of"] === "W")) return false;
+    if (obj.x === undefined) return false;
+    {
+      const val = obj.x;
+      if (!(typeof val === "number")) return false;
+    }
+    return true;
+  })()) return true;
+  return false;
+};"###);
+    }
+
+    #[test]
+    fn empty_enum() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            enum E {}
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export enum E {}");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isE = (obj: any): obj is E => {
+  return false;
+};"###);
+    }
+
+    #[test]
+    fn all_variants_skipped() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(enum_style = "union")]
+            enum E {
+                #[ts(skip)]
+                A,
+                #[ts(skip)]
+                B,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_string(), @"export type E = never;");
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(), @r###"export const isE = (obj: any): obj is E => {
+  return false;
+};"###);
+    }
+
+    #[test]
+    fn optional_field_may_be_missing() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            struct S {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                a: Option<i32>,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (!(obj.a === undefined)) {
+    const val = obj.a;
+    if (!(val === null)) {
+      if (!(typeof val === "number")) return false;
+    }
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn deny_unknown_fields_in_guard() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(deny_unknown_fields)]
+            struct S {
+                a: i32,
+                b: bool,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj.a === undefined) return false;
+  {
+    const val = obj.a;
+    if (!(typeof val === "number")) return false;
+  }
+  if (obj.b === undefined) return false;
+  {
+    const val = obj.b;
+    if (!(typeof val === "boolean")) return false;
+  }
+  for (let k in obj) {
+    if (!(k === "a" || k === "b")) return false;
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn strict_unknown_allows_undeclared_keys() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(strict = "unknown")]
+            struct S {
+                a: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj.a === undefined) return false;
+  {
+    const val = obj.a;
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn deny_unknown_is_separate_from_strict() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(deny_unknown = false, strict)]
+            struct S {
+                a: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj.a === undefined) return false;
+  {
+    const val = obj.a;
+    if (!(typeof val === "number")) return false;
+  }
+  return true;
+};"###
+        );
+
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[ts(deny_unknown)]
+            struct S {
+                a: i32,
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isS = (obj: any): obj is S => {
+  if (obj == undefined) return false;
+  if (obj.a === undefined) return false;
+  {
+    const val = obj.a;
+    if (!(typeof val === "number")) return false;
+  }
+  for (let k in obj) {
+    if (!(k === "a")) return false;
+  }
+  return true;
+};"###
+        );
+    }
+
+    #[test]
+    fn strict_enum_allows_tag_and_content() {
+        let tokens = quote!(
+            #[derive(Serialize)]
+            #[serde(tag = "kind", content = "data")]
+            #[ts(strict)]
+            enum E {
+                V { a: i32 },
+            }
+        );
+        let ty = Typescriptify::parse(true, tokens).unwrap();
+        assert_snapshot_matches!(ty.wasm_verify().unwrap(),
+        @r###"export const isE = (obj: any): obj is E => {
+  if (obj == undefined) return false;
+  if ((() => {
+    if (!(obj.kind === "V")) return false;
+    for (let k in obj) {
+      if (!(k === "kind" || k === "data")) return false;
+    }
+    const v = obj.data;
+    if (v == undefined) return false;
+    if (v.a === undefined) return false;
+    {
+      const val = v.a;
+      if (!(typeof val === "number")) return false;
+    }
+    for (let k in v) {
+      if (!(k === "a")) return false;
+    }
+    return true;
+  })()) return true;
+  return false;
+};"###
+        );
     }
 
     #[test]
@@ -58,10 +1415,10 @@ mod macro_test {
                 b: f64,
             }
         );
-        let result = std::panic::catch_unwind(move || Typescriptify::parse(true, tokens));
+        let result = Typescriptify::parse(true, tokens);
         match result {
-            Ok(_x) => assert!(false, "expecting panic!"),
-            Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
+            Ok(_x) => panic!("expecting errors!"),
+            Err(ref errors) => assert_snapshot_matches!(messages(errors),
             @r###"S: guard must be true or false not ""blah"""###
             ),
         }
@@ -77,7 +1434,7 @@ mod macro_test {
                 b: Vec<T>,
             }
         );
-        let ty = Typescriptify::parse(true, tokens);
+        let ty = Typescriptify::parse(true, tokens).unwrap();
         let i = &ty.ctxt.ident;
         let g = ty.ctxt.global_attrs.turbofish.unwrap_or_else(|| quote!());
         let res = quote!(#i#g::type_script_ify()).to_string();
@@ -97,13 +1454,12 @@ mod macro_test {
                 b: Vec<T>,
             }
         );
-        let result = std::panic::catch_unwind(move || Typescriptify::parse(false, tokens));
+        let result = Typescriptify::parse(false, tokens);
         match result {
-            Ok(_x) => assert!(false, "expecting panic!"),
-            Err(ref msg) => assert_snapshot_matches!( msg.downcast_ref::<String>().unwrap(),
-            @r###"2 errors:
-	# ts_as: "😀i32>" is not a valid rust type
-	# ts_as: "T[]" is not a valid rust type"###
+            Ok(_x) => panic!("expecting errors!"),
+            Err(ref errors) => assert_snapshot_matches!(messages(errors),
+            @r###"ts_as: "😀i32>" is not a valid rust type
+ts_as: "T[]" is not a valid rust type"###
             ),
         }
     }
@@ -5,30 +5,50 @@
 // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use super::{
-    ident_from_str, is_bytes, last_path_element, return_type, FieldContext, QuoteT, TSType,
-};
-use proc_macro2::Ident;
-use quote::quote;
+use super::attrs::Strict;
+use super::tsast::{Prop, Ty};
+use super::{is_bytes, last_path_element, return_type, ts_ident, FieldContext, TSType};
+use quote::{quote, ToTokens};
 
 impl<'a> FieldContext<'a> {
-    #[allow(clippy::cyclomatic_complexity)]
-    fn generic_to_ts(&self, ts: &TSType) -> QuoteT {
+    /// a rust type with no typescript equivalent: with `#[ts(strict)]` this
+    /// is an error, with `#[ts(strict = "unknown")]` it is `unknown`
+    /// otherwise `None` and the caller makes a best guess.
+    fn unmappable<A: ToTokens>(&self, tokens: A, what: String) -> Option<Ty> {
+        match self.ctxt.global_attrs.strict {
+            Strict::Off => None,
+            Strict::Unknown => Some(Ty::named("unknown")),
+            Strict::Deny => {
+                let msg = format!(
+                    "{}: can't map {} to typescript, use #[ts(ts_as = \"...\")] or #[ts(ts_type = \"...\")]",
+                    self.ctxt.ident, what
+                );
+                self.ctxt.err_msg(tokens, &msg);
+                Some(Ty::named("any"))
+            }
+        }
+    }
+
+    #[allow(clippy::cognitive_complexity)]
+    fn generic_to_ts(&self, ts: &TSType) -> Ty {
         let to_ts = |ty: &syn::Type| self.type_to_ts(ty);
         let name = ts.ident.to_string();
         match name.as_ref() {
             "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
-            | "i128" | "isize" | "f64" | "f32" => quote! { number },
-            "String" | "str" | "char" | "Path" | "PathBuf" => quote! { string },
-            "bool" => quote! { boolean },
+            | "i128" | "isize" | "f64" | "f32" => Ty::named("number"),
+            "String" | "str" | "char" | "Path" | "PathBuf" => Ty::named("string"),
+            "bool" => Ty::named("boolean"),
             "Box" | "Cow" | "Rc" | "Arc" | "Cell" | "RefCell" if ts.args.len() == 1 => {
                 to_ts(&ts.args[0])
             }
-            "Duration" => quote! ({ secs: number, nanos: number }),
-            "SystemTime" => quote! ({
-                secs_since_epoch: number,
-                nanos_since_epoch: number
-            }),
+            "Duration" => Ty::Object(vec![
+                Prop::new("secs", Ty::named("number")),
+                Prop::new("nanos", Ty::named("number")),
+            ]),
+            "SystemTime" => Ty::Object(vec![
+                Prop::new("secs_since_epoch", Ty::named("number")),
+                Prop::new("nanos_since_epoch", Ty::named("number")),
+            ]),
             // std::collections
             "Vec" | "VecDeque" | "LinkedList" if ts.args.len() == 1 => {
                 self.type_to_array(&ts.args[0])
@@ -36,78 +56,101 @@ impl<'a> FieldContext<'a> {
             "HashMap" | "BTreeMap" if ts.args.len() == 2 => {
                 let k = to_ts(&ts.args[0]);
                 let v = to_ts(&ts.args[1]);
-                // quote!(Map<#k,#v>)
-                quote!( { [key: #k]:#v } )
-            }
-            "HashSet" | "BTreeSet" if ts.args.len() == 1 => {
-                let k = to_ts(&ts.args[0]);
-                //quote!(Set<#k>)
-                quote! ( #k[] )
+                Ty::Index(Box::new(k), Box::new(v))
             }
+            "HashSet" | "BTreeSet" if ts.args.len() == 1 => Ty::array(to_ts(&ts.args[0])),
             "Option" if ts.args.len() == 1 => {
-                let k = to_ts(&ts.args[0]);
-                quote!(  #k | null  )
+                Ty::Union(vec![to_ts(&ts.args[0]), Ty::named("null")])
             }
             "Result" if ts.args.len() == 2 => {
                 let k = to_ts(&ts.args[0]);
                 let v = to_ts(&ts.args[1]);
-                quote!(  { Ok : #k } | { Err : #v }  )
+                Ty::Union(vec![
+                    Ty::Object(vec![Prop::new("Ok", k)]),
+                    Ty::Object(vec![Prop::new("Err", v)]),
+                ])
             }
             "Either" if ts.args.len() == 2 => {
                 let k = to_ts(&ts.args[0]);
                 let v = to_ts(&ts.args[1]);
-                quote!(  { Left : #k } | { Right : #v }  )
+                Ty::Union(vec![
+                    Ty::Object(vec![Prop::new("Left", k)]),
+                    Ty::Object(vec![Prop::new("Right", v)]),
+                ])
             }
             "Fn" | "FnOnce" | "FnMut" => {
-                let args = self.derive_syn_types(&ts.args);
-                if let Some(ref rt) = ts.return_type {
-                    let rt = to_ts(rt);
-                    quote! { (#(#args),*) => #rt }
-                } else {
-                    quote! { (#(#args),*) => undefined }
+                if let Some(ty) = self.unmappable(&ts.ident, format!("closure `{}`", ts.ident)) {
+                    return ty;
                 }
+                let args = self
+                    .derive_syn_types(&ts.args)
+                    .enumerate()
+                    .map(|(idx, ty)| (format!("_dummy{}", idx), ty))
+                    .collect();
+                let rt = match ts.return_type {
+                    Some(ref rt) => to_ts(rt),
+                    None => Ty::named("undefined"),
+                };
+                Ty::Fn(args, Box::new(rt))
             }
             _ => {
                 let owned = ts.path();
                 let path: Vec<&str> = owned.iter().map(|s| s.as_ref()).collect();
                 match path[..] {
-                    ["chrono", "DateTime"] => quote!(string),
+                    ["chrono", "DateTime"] => Ty::named("string"),
+                    ["Self"] => self.ctxt.self_type(),
                     _ => {
-                        let ident = &ts.ident;
-                        if !ts.args.is_empty() {
-                            let args = self.derive_syn_types(&ts.args);
-                            quote! { #ident<#(#args),*> }
-                        } else {
-                            quote! {#ident}
-                        }
+                        let ident = ts_ident(&ts.ident).to_string();
+                        Ty::Ref(ident, self.derive_syn_types(&ts.args).collect())
                     }
                 }
             }
         }
     }
 
-    fn type_to_array(&self, elem: &syn::Type) -> QuoteT {
+    /// remember a user type so that we can check it implements `TypeScriptifyTrait`
+    fn referenced(&self, ty: &syn::Type, ts: &TSType) {
+        let is_generic = || {
+            ts.path().len() == 1
+                && self
+                    .ctxt
+                    .ts_generics
+                    .iter()
+                    .flatten()
+                    .any(|(ident, _)| *ident == ts.ident)
+        };
+        // ts_as can name a typescript type that doesn't exist in rust
+        let unchecked = self.attrs.unchecked || self.attrs.ts_as.is_some();
+        if unchecked || ts.ident == "Self" || is_generic() {
+            return;
+        }
+        let mut referenced = self.ctxt.referenced.borrow_mut();
+        if !referenced.contains(ty) {
+            referenced.push(ty.clone());
+        }
+    }
+
+    fn type_to_array(&self, elem: &syn::Type) -> Ty {
         // check for [u8] or Vec<u8>
 
         if let Some(ty) = self.get_path(elem) {
-            if ty.ident == "u8" && is_bytes(&self.field) {
-                return quote!(string);
+            if ty.ident == "u8" && is_bytes(self.field) {
+                return Ty::named("string");
             };
         };
 
-        let tp = self.type_to_ts(elem);
-        quote! { #tp[] }
+        Ty::array(self.type_to_ts(elem))
     }
     /// # convert a `syn::Type` rust type to a
-    /// `TokenStream` of typescript type: basically i32 => number etc.
+    /// typescript `Ty`: basically i32 => number etc.
     ///
     /// field is the current Field for which we are trying a conversion
-    pub fn type_to_ts(&self, ty: &syn::Type) -> QuoteT {
+    pub fn type_to_ts(&self, ty: &syn::Type) -> Ty {
         // `type_to_ts` recursively calls itself occationally
         // finding a Path which it hands to last_path_element
         // which generates a "simplified" TSType struct which
         // is handed to `generic_to_ts` which possibly "bottoms out"
-        // by generating tokens for typescript types.
+        // by generating typescript types.
 
         use syn::Type::*;
         use syn::{
@@ -115,22 +158,26 @@ impl<'a> FieldContext<'a> {
             TypeParen, TypePath, TypePtr, TypeReference, TypeSlice, TypeTraitObject, TypeTuple,
         };
         match ty {
-            Slice(TypeSlice { elem, .. })
-            | Array(TypeArray { elem, .. })
-            | Ptr(TypePtr { elem, .. }) => self.type_to_array(elem),
+            Slice(TypeSlice { elem, .. }) | Array(TypeArray { elem, .. }) => {
+                self.type_to_array(elem)
+            }
+            Ptr(TypePtr { elem, .. }) => self
+                .unmappable(ty, format!("raw pointer `{}`", quote!(#ty)))
+                .unwrap_or_else(|| self.type_to_array(elem)),
             Reference(TypeReference { elem, .. }) => self.type_to_ts(elem),
             // fn(a: A,b: B, c:C) -> D
             BareFn(TypeBareFn { output, inputs, .. }) => {
-                let mut args: Vec<Ident> = Vec::with_capacity(inputs.len());
+                if let Some(ty) = self.unmappable(ty, format!("function `{}`", quote!(#ty))) {
+                    return ty;
+                }
+                let mut args: Vec<String> = Vec::with_capacity(inputs.len());
                 let mut typs: Vec<&syn::Type> = Vec::with_capacity(inputs.len());
 
                 for (idx, t) in inputs.iter().enumerate() {
                     let i = match t.name {
-                        Some((ref n, _)) => match n {
-                            BareFnArgName::Named(m) => m.clone(),
-                            _ => ident_from_str("_"), // Wild token '_'
-                        },
-                        _ => ident_from_str(&format!("_dummy{}", idx)),
+                        Some((BareFnArgName::Named(ref m), _)) => ts_ident(m).to_string(),
+                        // a Wild token '_' can't be repeated in typescript
+                        _ => format!("_dummy{}", idx),
                     };
                     args.push(i);
                     typs.push(&t.ty); // TODO: check type is known
@@ -139,23 +186,40 @@ impl<'a> FieldContext<'a> {
 
                 // let typs = typs.iter().map(|ty| self.type_to_ts(ty));
                 let typs = self.derive_syn_types_ptr(&typs);
-                if let Some(ref rt) = return_type(&output) {
-                    let rt = self.type_to_ts(rt);
-                    quote! { ( #(#args: #typs),* ) => #rt }
-                } else {
-                    quote! { ( #(#args: #typs),* ) => undefined}
-                }
+                let rt = match return_type(output) {
+                    Some(ref rt) => self.type_to_ts(rt),
+                    None => Ty::named("undefined"),
+                };
+                Ty::Fn(args.into_iter().zip(typs).collect(), Box::new(rt))
             }
-            Never(..) => quote! { never },
+            Never(..) => Ty::named("never"),
             Tuple(TypeTuple { elems, .. }) => {
-                let elems = elems.iter().map(|t| self.type_to_ts(t));
-                quote!([ #(#elems),* ])
+                Ty::Tuple(elems.iter().map(|t| self.type_to_ts(t)).collect())
             }
 
-            Path(TypePath { path, .. }) => match last_path_element(&path) {
-                Some(ref ts) => self.generic_to_ts(ts),
-                _ => quote! { any },
-            },
+            Path(TypePath { qself, path }) => {
+                // <T as Trait>::Assoc
+                if qself.is_some() {
+                    let what = format!("associated type `{}`", quote!(#ty));
+                    if let Some(ty) = self.unmappable(ty, what) {
+                        return ty;
+                    }
+                }
+                match last_path_element(path) {
+                    Some(ref ts) => {
+                        let result = self.generic_to_ts(ts);
+                        // i.e. not one of the types we know about
+                        match result {
+                            Ty::Ref(ref name, _) if ts_ident(&ts.ident) == name => {
+                                self.referenced(ty, ts)
+                            }
+                            _ => {}
+                        }
+                        result
+                    }
+                    _ => Ty::named("any"),
+                }
+            }
             TraitObject(TypeTraitObject { bounds, .. })
             | ImplTrait(TypeImplTrait { bounds, .. }) => {
                 let elems = bounds
@@ -164,26 +228,27 @@ impl<'a> FieldContext<'a> {
                         TypeParamBound::Trait(t) => last_path_element(&t.path),
                         _ => None, // skip lifetime etc.
                     })
-                    .map(|t| self.generic_to_ts(&t));
+                    .map(|t| self.generic_to_ts(&t))
+                    .collect();
 
                 // TODO check for zero length?
                 // A + B + C => A & B & C
-                quote!(#(#elems)&*)
-            }
-            Paren(TypeParen { elem, .. }) | Group(TypeGroup { elem, .. }) => {
-                let tp = self.type_to_ts(elem);
-                quote! { ( #tp ) }
+                Ty::intersection(elems)
             }
-            Infer(..) | Macro(..) | Verbatim(..) => quote! { any },
+            // the printer adds any parentheses that are needed
+            Paren(TypeParen { elem, .. }) | Group(TypeGroup { elem, .. }) => self.type_to_ts(elem),
+            Infer(..) | Macro(..) | Verbatim(..) => self
+                .unmappable(ty, format!("type `{}`", quote!(#ty)))
+                .unwrap_or_else(|| Ty::named("any")),
         }
     }
     pub fn derive_syn_types_ptr(
         &'a self,
         types: &'a [&'a syn::Type],
-    ) -> impl Iterator<Item = QuoteT> + 'a {
+    ) -> impl Iterator<Item = Ty> + 'a {
         types.iter().map(move |ty| self.type_to_ts(ty))
     }
-    pub fn derive_syn_types(&'a self, types: &'a [syn::Type]) -> impl Iterator<Item = QuoteT> + 'a {
+    pub fn derive_syn_types(&'a self, types: &'a [syn::Type]) -> impl Iterator<Item = Ty> + 'a {
         types.iter().map(move |ty| self.type_to_ts(ty))
     }
 }
@@ -0,0 +1,247 @@
+// Copyright 2019 Ian Castleden
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # TS AST
+//!
+//! A small typed model of what we generate: typescript types for the
+//! definitions and javascript statements for the type guards. It is
+//! built by `tots`, `guards` and the `derive_*` mods and turned into
+//! text by the `printer`.
+
+/// A literal value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lit {
+    Str(String),
+    /// a number e.g. an enum discriminant
+    Num(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ty {
+    /// `number`, `T` or `Foo<A, B>`
+    Ref(String, Vec<Ty>),
+    Lit(Lit),
+    /// a user supplied `#[ts(ts_type = "...")]`, printed as is
+    Raw(String),
+    Array(Box<Ty>),
+    Tuple(Vec<Ty>),
+    Object(Vec<Prop>),
+    /// `{ [key: K]: V }`
+    Index(Box<Ty>, Box<Ty>),
+    Union(Vec<Ty>),
+    Intersection(Vec<Ty>),
+    /// `(a: A, b: B) => R`
+    Fn(Vec<(String, Ty)>, Box<Ty>),
+    /// `/** doc */ T`, a documented union member
+    Doc(String, Box<Ty>),
+}
+
+/// A property of an object type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prop {
+    pub key: String,
+    pub optional: bool,
+    pub readonly: bool,
+    pub ty: Ty,
+    pub doc: Option<String>,
+}
+
+/// A member of a typescript enum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub key: String,
+    pub value: Lit,
+    pub doc: Option<String>,
+}
+
+impl Ty {
+    pub fn named(name: &str) -> Ty {
+        Ty::Ref(name.to_string(), vec![])
+    }
+    pub fn string(s: &str) -> Ty {
+        Ty::Lit(Lit::Str(s.to_string()))
+    }
+    pub fn array(elem: Ty) -> Ty {
+        Ty::Array(Box::new(elem))
+    }
+    /// `A & B`, or just `A`
+    pub fn intersection(mut types: Vec<Ty>) -> Ty {
+        if types.len() == 1 {
+            types.remove(0)
+        } else {
+            Ty::Intersection(types)
+        }
+    }
+    pub fn with_doc(self, doc: Option<String>) -> Ty {
+        match doc {
+            Some(doc) => Ty::Doc(doc, Box::new(self)),
+            None => self,
+        }
+    }
+}
+
+impl Prop {
+    pub fn new(key: &str, ty: Ty) -> Prop {
+        Prop {
+            key: key.to_string(),
+            optional: false,
+            readonly: false,
+            ty,
+            doc: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Ident(String),
+    Lit(Lit),
+    /// `obj.key` or `obj["key"]`
+    Member(Box<Expr>, String),
+    /// `obj[k]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `f<A, B>(x, y)`
+    Call(Box<Expr>, Vec<Ty>, Vec<Expr>),
+    /// `(a, b) => { ... }`
+    Arrow(Vec<String>, Vec<Stmt>),
+    Array(Vec<Expr>),
+    /// `!x`, `+x` or `typeof x`
+    Unary(&'static str, Box<Expr>),
+    /// `x === y`, `x == y`, `x || y` ...
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    If(Expr, Box<Stmt>),
+    Return(Expr),
+    Const(String, Expr),
+    Let(String, Expr),
+    /// `const { key: _, ...name } = obj;` i.e. `obj` without `key`
+    Omit(String, String, Expr),
+    ForOf(String, Expr, Vec<Stmt>),
+    ForIn(String, Expr, Vec<Stmt>),
+    Block(Vec<Stmt>),
+    Continue,
+    Break,
+}
+
+impl Expr {
+    pub fn ident(name: &str) -> Expr {
+        Expr::Ident(name.to_string())
+    }
+    pub fn string(s: &str) -> Expr {
+        Expr::Lit(Lit::Str(s.to_string()))
+    }
+    pub fn number(n: usize) -> Expr {
+        Expr::Lit(Lit::Num(n.to_string()))
+    }
+    pub fn member(&self, key: &str) -> Expr {
+        Expr::Member(Box::new(self.clone()), key.to_string())
+    }
+    pub fn index(&self, idx: Expr) -> Expr {
+        Expr::Index(Box::new(self.clone()), Box::new(idx))
+    }
+    pub fn call(self, args: Vec<Expr>) -> Expr {
+        Expr::Call(Box::new(self), vec![], args)
+    }
+    /// `(() => { ... })()`
+    pub fn iife(body: Vec<Stmt>) -> Expr {
+        Expr::Arrow(vec![], body).call(vec![])
+    }
+    pub fn not(self) -> Expr {
+        Expr::Unary("!", Box::new(self))
+    }
+    pub fn binary(self, op: &'static str, rhs: Expr) -> Expr {
+        Expr::Binary(Box::new(self), op, Box::new(rhs))
+    }
+    /// `self === rhs`
+    pub fn eq(self, rhs: Expr) -> Expr {
+        self.binary("===", rhs)
+    }
+    /// `self == rhs` i.e. also true for `null` and `undefined`
+    pub fn loose_eq(self, rhs: Expr) -> Expr {
+        self.binary("==", rhs)
+    }
+    /// `typeof self === "name"`
+    pub fn type_is(&self, name: &str) -> Expr {
+        Expr::Unary("typeof", Box::new(self.clone())).eq(Expr::string(name))
+    }
+    /// `Array.isArray(self)`
+    pub fn is_array(&self) -> Expr {
+        Expr::ident("Array")
+            .member("isArray")
+            .call(vec![self.clone()])
+    }
+    /// `a || b || c`
+    /// `a || b || c` (or `false` for none)
+    pub fn or(exprs: Vec<Expr>) -> Expr {
+        let mut exprs = exprs.into_iter();
+        match exprs.next() {
+            Some(first) => exprs.fold(first, |acc, e| acc.binary("||", e)),
+            None => Expr::ident("false"),
+        }
+    }
+    pub fn undefined() -> Expr {
+        Expr::ident("undefined")
+    }
+    pub fn null() -> Expr {
+        Expr::ident("null")
+    }
+}
+
+impl Stmt {
+    /// `if (cond) return false;`
+    pub fn fail_if(cond: Expr) -> Stmt {
+        Stmt::If(cond, Box::new(Stmt::ret(false)))
+    }
+    /// `if (!(cond)) return false;`
+    pub fn check(cond: Expr) -> Stmt {
+        Stmt::fail_if(cond.not())
+    }
+    pub fn ret(value: bool) -> Stmt {
+        Stmt::Return(Expr::ident(if value { "true" } else { "false" }))
+    }
+}
+
+/// A top level typescript declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decl {
+    /// `export type Name<T> = ...;`
+    Type {
+        name: String,
+        params: Vec<String>,
+        ty: Ty,
+    },
+    /// `export enum Name { A = "a", B = "b" }`
+    Enum {
+        name: String,
+        is_const: bool,
+        members: Vec<Member>,
+    },
+    /// `export const Name = [...] as const;`
+    Const {
+        name: String,
+        value: Expr,
+    },
+    Guard(Guard),
+    /// a declaration with a JSDoc comment
+    Doc(String, Box<Decl>),
+}
+
+/// `export const isName = <T>(obj: any, typename: string): obj is Name<T> => { ... };`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Guard {
+    pub name: String,
+    pub params: Vec<String>,
+    pub arg: String,
+    /// generic guards are also given the name of the type parameter
+    pub typename: bool,
+    pub ty: Ty,
+    pub body: Vec<Stmt>,
+}
@@ -1,42 +1,133 @@
 #![allow(unused)]
 
-use super::patch::{eq, nl};
-use failure::{Error, Fail};
+use super::guards::verify_length;
+use super::tsast::{Expr, Stmt};
+use failure::Error;
 use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::Parser;
-use proc_macro2::Literal;
-use proc_macro2::TokenStream;
-use quote::quote;
 
-use proc_macro2::{Ident, Span};
+/// A grammar error in a `ts_type` or `ts_guard` string.
+#[derive(Debug)]
+pub struct TypescriptParseError {
+    error: Box<pest::error::Error<Rule>>,
+    input: String,
+}
 
-pub fn ident_from_str(s: &str) -> Ident {
-    syn::Ident::new(s, Span::call_site())
+impl std::fmt::Display for TypescriptParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.row() > 1 {
+            write!(
+                f,
+                "{} at line {}, column {}",
+                self.message(),
+                self.row(),
+                self.column()
+            )
+        } else {
+            write!(f, "{} at column {}", self.message(), self.column())
+        }
+    }
 }
 
-#[derive(Fail, Debug)]
-#[fail(display = "{}", _0)]
-pub struct TypescriptParseError(pest::error::Error<Rule>);
+impl std::error::Error for TypescriptParseError {}
+
+/// tokens that can start a type: reported as just "a type"
+const TYPE_START: &[&str] = &[
+    "(", "[", "{", "number", "object", "string", "boolean", "null",
+];
 
 impl TypescriptParseError {
-    /// Return the column of where the error ocurred.
-    #[allow(unused)]
-    pub fn column(&self) -> usize {
-        match self.0.line_col {
-            pest::error::LineColLocation::Pos((_, col)) => col,
-            pest::error::LineColLocation::Span((_, col), _) => col,
+    fn new(input: &str, error: pest::error::Error<Rule>) -> Self {
+        TypescriptParseError {
+            error: Box::new(error),
+            input: input.to_string(),
         }
     }
-    #[allow(unused)]
+    /// byte offset into the input of where the error occurred.
+    fn position(&self) -> usize {
+        let pos = match self.error.parse_attempts() {
+            Some(ref attempts) => attempts.max_position,
+            None => match self.error.location {
+                pest::error::InputLocation::Pos(pos) => pos,
+                pest::error::InputLocation::Span((pos, _)) => pos,
+            },
+        };
+        pos.min(self.input.len())
+    }
+    /// Return the column of where the error ocurred (starting at 1).
+    pub fn column(&self) -> usize {
+        let before = &self.input[..self.position()];
+        before.chars().rev().take_while(|c| *c != '\n').count() + 1
+    }
+    /// Return the line of where the error ocurred (starting at 1).
     pub fn row(&self) -> usize {
-        match self.0.line_col {
-            pest::error::LineColLocation::Pos((row, _)) => row,
-            pest::error::LineColLocation::Span((row, _), _) => row,
+        self.input[..self.position()].matches('\n').count() + 1
+    }
+    /// e.g. "expected `]`, found `number`"
+    pub fn message(&self) -> String {
+        let expected = self.expected();
+        if expected.is_empty() {
+            return format!("unexpected {}", self.found());
+        }
+        let expected = match expected.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+            _ => expected.join(""),
+        };
+        format!("expected {}, found {}", expected, self.found())
+    }
+
+    fn expected(&self) -> Vec<String> {
+        let attempts = match self.error.parse_attempts() {
+            Some(attempts) => attempts,
+            None => return vec![],
+        };
+        let deepest = |rule: Rule| {
+            attempts
+                .call_stacks
+                .iter()
+                .any(|c| c.deepest.get_rule() == Some(&rule))
+        };
+        let mut expected = vec![];
+        if deepest(Rule::singleton) {
+            expected.push("a type".to_string());
+        }
+        for token in attempts.expected_tokens() {
+            let token = token.to_string();
+            if token == "BUILTIN_RULE" {
+                // WHITESPACE, EOI ... or an identifier
+                if deepest(Rule::ident) {
+                    expected.push("a field name".to_string());
+                }
+            } else if !(deepest(Rule::singleton) && TYPE_START.contains(&token.as_str())) {
+                expected.push(format!("`{}`", token));
+            }
+        }
+        expected
+    }
+
+    fn found(&self) -> String {
+        let rest = &self.input[self.position()..];
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+        match rest.chars().next() {
+            None => "end of string".to_string(),
+            Some(c) if is_word(&c) => {
+                format!("`{}`", rest.chars().take_while(is_word).collect::<String>())
+            }
+            Some(c) => format!("`{}`", c),
         }
     }
 }
 
+/// parse with enough detail to give a useful error message.
+fn parse_typescript(
+    typescript: &str,
+) -> Result<pest::iterators::Pairs<'_, Rule>, TypescriptParseError> {
+    pest::set_error_detail(true);
+    TypescriptParser::parse(Rule::typescript, typescript)
+        .map_err(|e| TypescriptParseError::new(typescript, e))
+}
+
 #[derive(Parser)]
 #[grammar = "typescript.pest"]
 struct TypescriptParser;
@@ -47,7 +138,7 @@ pub struct Typescript {
 }
 
 struct Ret {
-    result: TokenStream,
+    result: Vec<Stmt>,
     need_undef: bool,
 }
 
@@ -59,12 +150,11 @@ impl Typescript {
         Typescript { only_first, var: 0 }
     }
     pub fn verify(typescript: &str) -> Result<pest::iterators::Pairs<'_, Rule>, Error> {
-        Ok(TypescriptParser::parse(Rule::typescript, typescript).map_err(TypescriptParseError)?)
+        Ok(parse_typescript(typescript)?)
     }
 
-    pub fn parse(&mut self, obj: &TokenStream, typescript: &str) -> Result<TokenStream, Error> {
-        let pair = TypescriptParser::parse(Rule::typescript, typescript)
-            .map_err(TypescriptParseError)?
+    pub fn parse(&mut self, obj: &Expr, typescript: &str) -> Result<Vec<Stmt>, Error> {
+        let pair = parse_typescript(typescript)?
             .next() // skip SOI
             .unwrap();
         let mut content = vec![];
@@ -81,26 +171,19 @@ impl Typescript {
             });
         }
         assert!(content.len() == 1);
-        let newl = nl();
 
         // obj can't be null or undefined
         assert!(self.level() == 0);
-        let guard = if need_undef {
-            let eq = eq();
-            quote!(if (#obj #eq undefined) return false;)
-        } else {
-            quote!()
+        let mut block = vec![];
+        if need_undef {
+            block.push(Stmt::fail_if(obj.clone().eq(Expr::undefined())));
         };
+        block.extend(content.into_iter().flatten());
+        block.push(Stmt::ret(true));
 
-        Ok(quote!(
-            {
-                #guard
-                #(#content)*
-                #newl return true;
-            }
-        ))
+        Ok(vec![Stmt::Block(block)])
     }
-    fn parse_expr<'a>(&mut self, obj: &TokenStream, expr: Pair<'a, Rule>) -> Result<Ret, Error> {
+    fn parse_expr<'a>(&mut self, obj: &Expr, expr: Pair<'a, Rule>) -> Result<Ret, Error> {
         // expr = { union | "(" ~ expr ~ ")" }
 
         let mut content = vec![];
@@ -110,31 +193,30 @@ impl Typescript {
             content.push(match u.as_rule() {
                 Rule::union => {
                     is_union = true;
-                    let (q, n) = self.parse_union(&obj, u)?;
+                    let (q, n) = self.parse_union(obj, u)?;
                     size = n;
                     q
                 }
-                Rule::expr => self.parse_expr(&obj, u)?,
+                Rule::expr => self.parse_expr(obj, u)?,
 
                 _ => unreachable!(),
             })
         }
         assert!(content.len() == 1);
 
-        let c = &content[0].result;
-        let n = content[0].need_undef;
+        let Ret { result, need_undef } = content.remove(0);
 
         let test = if is_union && size > 1 {
-            quote!(if ( !( () => { #c } )() ) return false;)
+            vec![Stmt::check(Expr::iife(result))]
         } else {
-            quote!( #c )
+            result
         };
         Ok(Ret {
             result: test,
-            need_undef: n,
+            need_undef,
         })
     }
-    fn parse_item<'a>(&mut self, obj: &TokenStream, item: Pair<'a, Rule>) -> Result<Ret, Error> {
+    fn parse_item<'a>(&mut self, obj: &Expr, item: Pair<'a, Rule>) -> Result<Ret, Error> {
         let mut i = item.into_inner();
         // item = { singleton ~ array  }
         let (singleton, array) = (i.next().unwrap(), i.next().unwrap());
@@ -163,67 +245,61 @@ impl Typescript {
             });
         }
         assert!(content.len() == 1);
-        let c = &content[0].result;
-        let n = content[0].need_undef;
+        let Ret { result, need_undef } = content.remove(0);
         let test = if is_union && size > 1 {
-            quote!(if ( !( () => { #c } )() ) return false;)
+            vec![Stmt::check(Expr::iife(result))]
         } else {
-            quote!( #c )
+            result
         };
         if narr == 0 {
             // self.popvar();
             Ok(Ret {
                 result: test,
-                need_undef: n,
+                need_undef,
             })
         } else {
-            let brk = if self.only_first {
-                quote!(break;)
-            } else {
-                quote!()
+            let only_first = self.only_first;
+            let brk = |mut body: Vec<Stmt>| {
+                if only_first {
+                    body.push(Stmt::Break);
+                }
+                body
             };
+            let loop_var = |v: &Expr| v.to_string();
 
             let mut vinner = self.pushvar();
-            let mut inner = quote!(
-                {
-                    if (!Array.isArray(#vinner)) return false;
-                    for (let #val of #vinner) {
-                        #test
-                        #brk
-                    }
-                }
-            );
+            let mut inner = vec![Stmt::Block(vec![
+                Stmt::check(vinner.is_array()),
+                Stmt::ForOf(loop_var(val), vinner.clone(), brk(test)),
+            ])];
             for i in 0..narr - 1 {
                 let vnext = self.pushvar();
-                inner = quote!(
-                if (!Array.isArray(#vnext)) return false;
-                for (let #vinner of #vnext) {
-                    #inner
-                    #brk
-                });
+                inner = vec![
+                    Stmt::check(vnext.is_array()),
+                    Stmt::ForOf(loop_var(&vinner), vnext.clone(), brk(inner)),
+                ];
                 vinner = vnext;
             }
             for i in 0..narr {
                 self.popvar()
             }
+            let mut result = vec![Stmt::Const(loop_var(&vinner), obj.clone())];
+            result.extend(inner);
             Ok(Ret {
-                result: quote!(const #vinner = #obj; #inner;),
+                result,
                 need_undef: false,
             })
         }
     }
-    fn parse_typ<'a>(&mut self, obj: &TokenStream, typ: Pair<'a, Rule>) -> Result<Ret, Error> {
+    fn parse_typ<'a>(&mut self, obj: &Expr, typ: Pair<'a, Rule>) -> Result<Ret, Error> {
         // typ = { "number" | "object" | "string" | "boolean" | "null" }
         let typ = typ.as_str();
-        let eq = eq();
         Ok(Ret {
-            result: quote!(
-               if (!(typeof #obj #eq #typ)) return false;
-            ),
+            result: vec![Stmt::check(obj.type_is(typ))],
             need_undef: false,
         })
     }
-    fn parse_map<'a>(&mut self, obj: &TokenStream, map: Pair<'a, Rule>) -> Result<Ret, Error> {
+    fn parse_map<'a>(&mut self, obj: &Expr, map: Pair<'a, Rule>) -> Result<Ret, Error> {
         // map = {  "{" ~ "[" ~ "key" ~ ":" ~ key ~ "]" ~ ":" ~ expr ~ "}" }
         let mut i = map.into_inner();
         let (typ, expr) = (i.next().unwrap(), i.next().unwrap());
@@ -232,42 +308,32 @@ impl Typescript {
         // let typ = self.parse_typ(typ)?;
         let val = self.pushvar();
         let result = self.parse_expr(&val, expr)?;
-        let eq = eq();
         let kval = self.pushvar();
-        let k = if k == "number" {
-            quote! {
-                if (+#kval #eq NaN) return false;
-            }
-        } else {
-            // always going to be a string
-            quote!()
-        };
-        let brk = if self.only_first {
-            quote!(break;)
-        } else {
-            quote!()
-        };
+        // val is not undefined....
+        let mut body = vec![Stmt::Let(val.to_string(), obj.index(kval.clone()))];
+        if k == "number" {
+            let k = Expr::Unary("+", Box::new(kval.clone()));
+            body.push(Stmt::fail_if(k.eq(Expr::ident("NaN"))));
+        }
+        // otherwise always going to be a string
+        body.extend(result.result);
+        if self.only_first {
+            body.push(Stmt::Break);
+        }
         self.popvar();
         self.popvar();
         // obj is definitely not undefined... but it might be null...
-        let v = result.result;
         Ok(Ret {
-            result: quote!(
-                if (!(typeof #obj #eq "object")) return false;
-                for (let #kval in #obj) {
-                    let #val = #obj[#kval];
-                    // #val is not undefined....
-                    #k;
-                    #v;
-                    #brk
-                }
-            ),
+            result: vec![
+                Stmt::check(obj.type_is("object")),
+                Stmt::ForIn(kval.to_string(), obj.clone(), body),
+            ],
             need_undef: false,
         })
     }
     fn parse_union<'a>(
         &mut self,
-        obj: &TokenStream,
+        obj: &Expr,
         union: Pair<'a, Rule>,
     ) -> Result<(Ret, usize), Error> {
         // union = {   item ~ ("|" ~ item)*  }
@@ -279,26 +345,25 @@ impl Typescript {
                 _ => unreachable!(),
             }
         }
-        let newl = nl();
-        let nl = (0..results.len()).map(|_| quote!(#newl));
         // self.popvar();
         // obj can't be null or undefined
         let n = results.len();
+        let need = results.iter().any(|r| r.need_undef);
         // a *single* union doesn't need to check multiple failures
         // looking for a success....
-        let content = results.iter().map(|r| &r.result);
-        let need = results.iter().any(|r| r.need_undef);
         let ret = if n == 1 {
-            quote!(
-                #(#content)*
-            )
+            results.remove(0).result
         } else {
-            quote!(
-                {
-                #( #nl if ( ( () => { #content; return true; } )() ) return true; )*
-                #newl return false;
-                }
-            )
+            let mut block = results
+                .into_iter()
+                .map(|r| {
+                    let mut body = r.result;
+                    body.push(Stmt::ret(true));
+                    Stmt::If(Expr::iife(body), Box::new(Stmt::ret(true)))
+                })
+                .collect::<Vec<_>>();
+            block.push(Stmt::ret(false));
+            vec![Stmt::Block(block)]
         };
 
         Ok((
@@ -309,89 +374,72 @@ impl Typescript {
             n,
         ))
     }
-    fn parse_tuple<'a>(&mut self, obj: &TokenStream, tuple: Pair<'a, Rule>) -> Result<Ret, Error> {
+    fn parse_tuple<'a>(&mut self, obj: &Expr, tuple: Pair<'a, Rule>) -> Result<Ret, Error> {
         // tuple = { "[" ~ expr ~ ("," ~ expr )+ ~ "]" }
         let mut content = vec![];
-        let eq = eq();
+        let mut len = 0;
         let val = self.pushvar();
         for (i, expr) in tuple.into_inner().enumerate() {
-            let i = Literal::usize_unsuffixed(i);
-            let n = quote!(#obj[#i]);
+            let n = obj.index(Expr::number(i));
+            len += 1;
 
             match expr.as_rule() {
                 Rule::expr => {
                     let v = self.parse_expr(&val, expr)?;
-                    let verify = v.result;
-                    let guard = if v.need_undef {
-                        quote!(if (#n #eq undefined) return false;)
-                    } else {
-                        quote!()
+                    if v.need_undef {
+                        content.push(Stmt::fail_if(n.clone().eq(Expr::undefined())));
                     };
-                    content.push(quote! {
-                        #guard
-                        {
-                            const #val = #n;
-                            #verify;
-                        }
-                    });
+                    let mut block = vec![Stmt::Const(val.to_string(), n)];
+                    block.extend(v.result);
+                    content.push(Stmt::Block(block));
                 }
                 _ => unreachable!(),
             }
         }
         self.popvar();
-        let len = Literal::usize_unsuffixed(content.len());
         // isArray protects us from null obj
+        let mut result = vec![verify_length(obj, len)];
+        result.extend(content);
         Ok(Ret {
-            result: quote!(
-                if (!Array.isArray(#obj) || !(#obj.length #eq #len)) return false;
-                #(#content)*
-            ),
+            result,
             need_undef: false,
         })
     }
-    fn parse_struct<'a>(&mut self, obj: &TokenStream, pair: Pair<'a, Rule>) -> Result<Ret, Error> {
+    fn parse_struct<'a>(&mut self, obj: &Expr, pair: Pair<'a, Rule>) -> Result<Ret, Error> {
         // str = {  "{" ~ (ident ~ ":" ~ expr)? ~ ("," ~ ident ~ ":" ~ expr )* ~ "}" }
         let mut keys = vec![];
         let mut values = vec![];
         let val = self.pushvar();
         for expr in pair.into_inner() {
             match expr.as_rule() {
-                Rule::ident => keys.push(ident_from_str(&expr.as_str())),
+                Rule::ident => keys.push(expr.as_str()),
                 Rule::expr => values.push(self.parse_expr(&val, expr)?),
                 _ => unreachable!(),
             }
         }
-        let mut ret = vec![];
-        let eq = eq();
+        let mut ret = vec![Stmt::check(obj.type_is("object"))];
         for (n, v) in keys.iter().zip(values) {
-            let verify = v.result;
-            let guard = if v.need_undef {
-                quote!(if (#obj.#n #eq undefined) return false;)
-            } else {
-                quote!()
+            let n = obj.member(n);
+            if v.need_undef {
+                ret.push(Stmt::fail_if(n.clone().eq(Expr::undefined())));
             };
-            ret.push(quote! {
-                #guard
-                {
-                    const #val = #obj.#n;
-                    #verify
-                }
-            });
+            let mut block = vec![Stmt::Const(val.to_string(), n)];
+            block.extend(v.result);
+            ret.push(Stmt::Block(block));
         }
         self.popvar();
         // need to protect object access from the null object so ==
 
         Ok(Ret {
-            result: quote!(if( !(typeof #obj #eq "object")) return false; #(#ret)*),
+            result: ret,
             need_undef: false,
         })
     }
 
-    fn pushvar(&mut self) -> TokenStream {
+    fn pushvar(&mut self) -> Expr {
         self.var += 1;
 
-        let n = ident_from_str(&format!("val{}", self.var));
-        quote!(#n)
+        Expr::ident(&format!("val{}", self.var))
     }
     fn popvar(&mut self) {
         self.var -= 1;
@@ -405,23 +453,26 @@ impl Typescript {
 #[cfg(test)]
 mod parser {
     use super::Typescript;
-    use crate::patch::patch;
-    use quote::quote;
+    use crate::tsast::{Expr, Stmt};
     //#[test]
     fn typescript_parser() {
         let mut t = Typescript::new();
-        match t.parse(&quote!(obj), &"[number, string]|{ [key: number]: string}[][] | {a: number} | (number|{a:{b:number}})") {
-            Ok(q) => {eprintln!("{}", patch(&q.to_string()))},
-            Err(msg) => assert!(false, msg)
+        match t.parse(
+            &Expr::ident("obj"),
+            "[number, string]|{ [key: number]: string}[][] | {a: number} | (number|{a:{b:number}})",
+        ) {
+            Ok(q) => {
+                eprintln!("{}", Stmt::Block(q))
+            }
+            Err(msg) => panic!("{}", msg),
         }
     }
     #[test]
     fn typescript_parser2() {
         let mut t = Typescript::new();
-        match t.parse(&quote!(obj), &"[number, string][]") {
-            Ok(q) => eprintln!("{}", patch(&q.to_string())),
-            Err(msg) => assert!(false, msg),
+        match t.parse(&Expr::ident("obj"), "[number, string][]") {
+            Ok(q) => eprintln!("{}", Stmt::Block(q)),
+            Err(msg) => panic!("{}", msg),
         }
     }
-
 }
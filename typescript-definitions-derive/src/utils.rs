@@ -5,8 +5,10 @@
 // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use super::ast;
+use super::tsast::Lit;
+use super::{ast, unraw, Ctxt};
 use proc_macro2::{Ident, Span};
+use quote::quote;
 
 pub fn ident_from_str(s: &str) -> Ident {
     syn::Ident::new(s, Span::call_site())
@@ -15,10 +17,11 @@ pub fn ident_from_str(s: &str) -> Ident {
 pub fn field_type_name(ty: &syn::Type) -> Option<String> {
     use syn::Type::Path;
     match ty {
-        Path(syn::TypePath { path, .. }) => match path.segments.last().map(|p| p.into_value()) {
-            Some(t) => Some(t.ident.to_string()),
-            _ => None,
-        },
+        Path(syn::TypePath { path, .. }) => path
+            .segments
+            .last()
+            .map(|p| p.into_value())
+            .map(|t| t.ident.to_string()),
         _ => None,
     }
 }
@@ -66,11 +69,75 @@ pub fn filter_visible<'a>(fields: &'a [ast::Field<'a>]) -> Vec<&'a ast::Field<'a
     content
 }
 
-const GUARD_PREFIX: &str = "is";
+/// split fields into (`#[serde(flatten)]`, other) fields.
+pub fn split_flatten<'a>(
+    fields: Vec<&'a ast::Field<'a>>,
+) -> (Vec<&'a ast::Field<'a>>, Vec<&'a ast::Field<'a>>) {
+    fields.into_iter().partition(|f| f.attrs.flatten())
+}
+
+fn int_value(expr: &syn::Expr) -> Option<i128> {
+    use syn::{Expr, ExprLit, ExprUnary, Lit, UnOp};
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(i), ..
+        }) => Some(i128::from(i.value())),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(..),
+            expr,
+            ..
+        }) => int_value(expr).map(|i| -i),
+        _ => None,
+    }
+}
 
-pub fn guard_name(ident: &Ident) -> Ident {
-    let mut s = String::new();
-    s.push_str(GUARD_PREFIX);
-    s.push_str(&ident.to_string());
-    ident_from_str(&s)
+/// Does any variant of this enum have an explicit `= discriminant`?
+pub fn has_discriminants(data: &syn::Data) -> bool {
+    match data {
+        syn::Data::Enum(e) => e.variants.iter().any(|v| v.discriminant.is_some()),
+        _ => false,
+    }
+}
+
+/// The values of a C-like enum's variants (keyed by variant ident). As with rust
+/// a variant without a discriminant is one more than the previous variant.
+/// Only unit variants and integer literal discriminants make sense in typescript.
+pub fn discriminants(data: &syn::Data, ctxt: &Ctxt) -> Vec<(String, Lit)> {
+    let variants = match data {
+        syn::Data::Enum(e) => &e.variants,
+        _ => return vec![],
+    };
+    let mut n: i128 = -1;
+    variants
+        .iter()
+        .map(|v| {
+            if v.fields != syn::Fields::Unit {
+                ctxt.error_spanned_by(
+                    &v.ident,
+                    format!(
+                        "{}: only unit variants can be serialized as numbers",
+                        v.ident
+                    ),
+                );
+            }
+            match v.discriminant {
+                Some((_, ref expr)) => match int_value(expr) {
+                    Some(i) => n = i,
+                    None => {
+                        ctxt.error_spanned_by(
+                            expr,
+                            format!(
+                                "{}: discriminant `{}` must be an integer literal to be a typescript number",
+                                v.ident,
+                                quote!(#expr)
+                            ),
+                        );
+                        n += 1;
+                    }
+                },
+                None => n += 1,
+            }
+            (unraw(&v.ident), Lit::Num(n.to_string()))
+        })
+        .collect()
 }